@@ -1,6 +1,12 @@
 use app_surface::{AppSurface, SurfaceFrame};
 use bytemuck::{Pod, Zeroable};
-use utils::framework::run;
+use utils::{
+    framework::run,
+    mipmap::MipmapGenerator,
+    preprocess::Preprocessor,
+    texture::{Texture, TextureOptions},
+    vertex::VertexLayout,
+};
 use wgpu::util::DeviceExt;
 use winit::dpi::PhysicalSize;
 
@@ -10,12 +16,41 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     tex_coords: [f32; 2],
 }
 
+/// 注册了 `shader.wgsl` 和共用的 `common.wgsl` 的预处理器
+fn preprocessor() -> Preprocessor {
+    Preprocessor::new()
+        .with_common()
+        .with_file("shader.wgsl", include_str!("shader.wgsl"))
+}
+
+/// `@binding(0)` 为纹理，`@binding(1)` 为采样器
+fn texture_bind_group_layout_entries() -> [wgpu::BindGroupLayoutEntry; 2] {
+    [
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        },
+    ]
+}
+
 const VERTICES: &[Vertex] = &[
     // 修改后的
     Vertex {
@@ -40,10 +75,29 @@ const VERTICES: &[Vertex] = &[
     }, // E
 ];
 
+/// 带有完整的 mip 链，缩小显示时不会出现走样
+fn load_diffuse_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mipmaps: &MipmapGenerator,
+) -> Texture {
+    Texture::from_bytes(
+        device,
+        queue,
+        include_bytes!("../../happy-tree.png"),
+        Some("diffuse_texture"),
+        TextureOptions::mipmapped(mipmaps),
+    )
+    .unwrap()
+}
+
 const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
 
 struct WgpuApp {
-    app: AppSurface,
+    /// 无头模式下没有窗口表面
+    app: Option<AppSurface>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
     size: PhysicalSize<u32>,
     size_changed: bool,
     render_pipeline: wgpu::RenderPipeline,
@@ -55,195 +109,108 @@ struct WgpuApp {
 impl WgpuApp {
     fn resize(&mut self) {
         if self.size_changed {
-            self.app
-                .resize_surface_by_size((self.size.width, self.size.height));
+            if let Some(app) = self.app.as_mut() {
+                app.resize_surface_by_size((self.size.width, self.size.height));
+            }
             self.size_changed = false;
         }
     }
-}
-
-impl utils::framework::WgpuAppAction for WgpuApp {
-    async fn new(window: std::sync::Arc<winit::window::Window>) -> Self {
-        let app = AppSurface::new(window).await;
 
-        let diffuse_bytes = include_bytes!("../../happy-tree.png");
-        let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-        let diffuse_rgba = diffuse_image.to_rgba8();
-
-        use image::GenericImageView;
-        let dimensions = diffuse_image.dimensions();
-
-        let texture_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
-
-        let diffuse_texture = app.device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("diffuse_texture"),
-            view_formats: &[],
-        });
-
-        app.queue.write_texture(
-            wgpu::TexelCopyTextureInfoBase {
-                texture: &diffuse_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &diffuse_rgba,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            texture_size,
-        );
-
-        let diffuse_texture_view =
-            diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-        let diffuse_sampler = app.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+    fn create(
+        app: Option<AppSurface>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        let mipmaps = MipmapGenerator::new(&device);
+        let diffuse_texture = load_diffuse_texture(&device, &queue, &mipmaps);
 
         let texture_bind_group_layout =
-            app.device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                multisampled: false,
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                    label: Some("texture_bind_group_layout"),
-                });
-
-        let diffuse_bind_group = app.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &texture_bind_group_layout_entries(),
+                label: Some("texture_bind_group_layout"),
+            });
+
+        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &texture_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture_view),
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
                 },
             ],
             label: Some("diffuse_bind_group"),
         });
 
-        let shader = app
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("Shader"),
-                source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-            });
+        let shader = preprocessor().create_shader_module(&device, "Shader", "shader.wgsl");
 
         let render_pipeline_layout =
-            app.device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Render Pipeline Layout"),
-                    bind_group_layouts: &[&texture_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
-
-        let buffer = app
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(VERTICES),
-                usage: wgpu::BufferUsages::VERTEX,
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&texture_bind_group_layout],
+                push_constant_ranges: &[],
             });
 
-        let index_buffer = app
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(INDICES),
-                usage: wgpu::BufferUsages::INDEX,
-            });
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
 
-        let render_pipeline = app
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("render pipeline"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: Some("vs_main"),
-                    compilation_options: Default::default(),
-                    buffers: &[wgpu::VertexBufferLayout {
-                        array_stride: core::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![
-                            0 => Float32x3,
-                            1 => Float32x2,
-                        ],
-                    }],
-                },
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
 
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: Some("fs_main"),
-                    compilation_options: Default::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: app.config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                multiview: None,
-                cache: None,
-            });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("render pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Vertex::LAYOUT],
+            },
 
-        let size = PhysicalSize {
-            width: app.config.width,
-            height: app.config.height,
-        };
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
 
         Self {
             app,
+            device,
+            queue,
             size,
             size_changed: false,
             render_pipeline,
@@ -252,9 +219,26 @@ impl utils::framework::WgpuAppAction for WgpuApp {
             diffuse_bind_group,
         }
     }
+}
+
+impl utils::framework::WgpuAppAction for WgpuApp {
+    async fn new(window: std::sync::Arc<winit::window::Window>) -> Self {
+        let app = AppSurface::new(window).await;
+        let device = app.device.clone();
+        let queue = app.queue.clone();
+        let format = app.config.format;
+        let size = PhysicalSize::new(app.config.width, app.config.height);
+
+        Self::create(Some(app), device, queue, format, size)
+    }
+
+    async fn new_headless(ctx: utils::headless::HeadlessContext) -> Self {
+        let size = ctx.size();
+        Self::create(None, ctx.device, ctx.queue, ctx.config.format, size)
+    }
 
     fn set_window_size(&mut self, new_size: PhysicalSize<u32>) {
-        if self.app.config.width == new_size.width && self.app.config.height == new_size.height {
+        if self.get_size() == new_size {
             return;
         }
 
@@ -263,27 +247,37 @@ impl utils::framework::WgpuAppAction for WgpuApp {
     }
 
     fn get_size(&self) -> PhysicalSize<u32> {
-        PhysicalSize::new(self.app.config.width, self.app.config.height)
+        match self.app.as_ref() {
+            Some(app) => PhysicalSize::new(app.config.width, app.config.height),
+            None => self.size,
+        }
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let current_size = self.app.get_view().inner_size();
+        let Some(app) = self.app.as_ref() else {
+            return Ok(());
+        };
+        let current_size = app.get_view().inner_size();
 
         if current_size.height == 0 || current_size.width == 0 {
             return Ok(());
         }
 
-        if current_size.width != self.app.config.width
-            || current_size.height != self.app.config.height
-        {
+        if current_size != self.get_size() {
             self.size = current_size;
             self.size_changed = true;
         }
         self.resize();
 
-        let (output, view) = self.app.get_current_frame_view(None);
+        let (output, view) = self.app.as_ref().unwrap().get_current_frame_view(None);
+        self.render_to_view(&view);
+        output.present();
+
+        Ok(())
+    }
+
+    fn render_to_view(&mut self, view: &wgpu::TextureView) {
         let mut encoder = self
-            .app
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
@@ -293,7 +287,7 @@ impl utils::framework::WgpuAppAction for WgpuApp {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     depth_slice: None,
                     resolve_target: None,
                     ops: wgpu::Operations {
@@ -317,9 +311,42 @@ impl utils::framework::WgpuAppAction for WgpuApp {
             render_pass.draw_indexed(0..9, 0, 0..1);
         }
 
-        self.app.queue.submit(Some(encoder.finish()));
-        output.present();
+        self.queue.submit(Some(encoder.finish()));
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use utils::{headless::test_context, mipmap::MipmapGenerator, shader_check::ShaderCheck};
+
+    use super::{
+        Vertex, WgpuApp, load_diffuse_texture, preprocessor, texture_bind_group_layout_entries,
+    };
+
+    #[test]
+    fn shader_matches_pipeline() {
+        let shader = ShaderCheck::new(&preprocessor(), "shader.wgsl").unwrap();
+        shader.vertex_buffers("vs_main", &[Vertex::LAYOUT]).unwrap();
+        shader
+            .bind_groups(
+                &["vs_main", "fs_main"],
+                &[&texture_bind_group_layout_entries()],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn happy_tree_matches_golden() {
+        utils::assert_golden!(WgpuApp, "happy_tree");
+    }
+
+    #[test]
+    fn diffuse_texture_has_mipmaps() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+        let mipmaps = MipmapGenerator::new(&ctx.device);
+        let texture = load_diffuse_texture(&ctx.device, &ctx.queue, &mipmaps);
+        assert!(texture.texture.mip_level_count() > 1);
     }
 }
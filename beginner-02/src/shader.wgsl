@@ -1,12 +1,5 @@
-struct VertexOutput {
-    @builtin(position) clip_position: vec4f,
-    @location(0) tex_coords: vec2f,
-};
-
-struct VertexInput {
-    @location(0) position: vec3f,
-    @location(1) tex_coords: vec2f,
-};
+#define VERTEX_TEX_COORDS
+#include "common.wgsl"
 
 @vertex
 fn vs_main(
@@ -1,40 +1,238 @@
 use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Vec3};
+use utils::depth::DepthMapping;
+
+/// 无限远投影没有远平面，深度调试视图把这个距离及更远处显示为白色
+const INFINITE_DEPTH_DEBUG_FAR: f32 = 100.0;
+
+/// 投影变换，与相机的位置、朝向（视图变换）分开
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// `fovy` 为垂直视角，单位为度
+    Perspective { fovy: f32, znear: f32, zfar: f32 },
+    /// `height` 为可见区域的高度，宽度按宽高比计算
+    Orthographic { height: f32, znear: f32, zfar: f32 },
+    /// 无限远平面 + 反向 Z：近平面深度为 1，无穷远处深度为 0，远处精度更高
+    InfiniteReverseZ { fovy: f32, znear: f32 },
+}
+
+impl Projection {
+    pub fn build_projection_matrix(&self, aspect: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fovy, znear, zfar } => {
+                Mat4::perspective_rh(fovy.to_radians(), aspect, znear, zfar)
+            }
+            Projection::Orthographic {
+                height,
+                znear,
+                zfar,
+            } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect;
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    znear,
+                    zfar,
+                )
+            }
+            Projection::InfiniteReverseZ { fovy, znear } => {
+                Mat4::perspective_infinite_reverse_rh(fovy.to_radians(), aspect, znear)
+            }
+        }
+    }
+
+    /// 近平面和远平面在视图空间中的距离，无限远投影的远平面为 `f32::INFINITY`
+    pub fn clip_planes(&self) -> (f32, f32) {
+        match *self {
+            Projection::Perspective { znear, zfar, .. }
+            | Projection::Orthographic { znear, zfar, .. } => (znear, zfar),
+            Projection::InfiniteReverseZ { znear, .. } => (znear, f32::INFINITY),
+        }
+    }
+
+    pub fn is_reverse_z(&self) -> bool {
+        matches!(self, Projection::InfiniteReverseZ { .. })
+    }
+
+    /// 渲染管线使用的深度比较函数
+    pub fn depth_compare(&self) -> wgpu::CompareFunction {
+        if self.is_reverse_z() {
+            wgpu::CompareFunction::Greater
+        } else {
+            wgpu::CompareFunction::Less
+        }
+    }
+
+    /// 每帧清空深度缓冲时使用的值，即最远处的深度
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.is_reverse_z() { 0.0 } else { 1.0 }
+    }
+
+    /// 深度调试视图还原距离时使用的映射方式
+    pub fn depth_mapping(&self) -> DepthMapping {
+        match *self {
+            Projection::Perspective { znear, zfar, .. } => DepthMapping::Perspective {
+                near: znear,
+                far: zfar,
+            },
+            Projection::Orthographic { znear, zfar, .. } => DepthMapping::Orthographic {
+                near: znear,
+                far: zfar,
+            },
+            Projection::InfiniteReverseZ { znear, .. } => DepthMapping::InfiniteReverseZ {
+                near: znear,
+                far: INFINITE_DEPTH_DEBUG_FAR,
+            },
+        }
+    }
+}
 
 pub struct Camera {
     pub eye: Vec3,
     pub target: Vec3,
     pub up: Vec3,
     pub aspect: f32,
-    pub fovy: f32,
-    pub znear: f32,
-    pub zfar: f32,
+    pub projection: Projection,
 }
 
 impl Camera {
-    fn build_view_projection_matrix(&self) -> Mat4 {
-        let view = Mat4::look_at_rh(self.eye, self.target, self.up);
-
-        let proj = Mat4::perspective_rh(self.fovy.to_radians(), self.aspect, self.znear, self.zfar);
+    pub fn build_view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
 
-        proj * view
+    pub fn build_projection_matrix(&self) -> Mat4 {
+        self.projection.build_projection_matrix(self.aspect)
     }
 }
 
+/// 与 `camera.wgsl` 中的 `CameraUniform` 一一对应
+///
+/// WGSL 中 `vec3f` 按 16 字节对齐，结构体大小也必须是 16 的倍数，
+/// 所以 `eye` 和 `viewport` 后面都需要手动补齐
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 pub struct CameraUniform {
     pub view_proj: [[f32; 4]; 4],
+    pub view: [[f32; 4]; 4],
+    pub proj: [[f32; 4]; 4],
+    pub inv_view: [[f32; 4]; 4],
+    pub inv_proj: [[f32; 4]; 4],
+    /// 用于从深度重建世界坐标
+    pub inv_view_proj: [[f32; 4]; 4],
+    /// 相机在世界空间中的位置
+    pub eye: [f32; 3],
+    _padding: f32,
+    /// 渲染目标的宽高，单位为像素
+    pub viewport: [f32; 2],
+    _padding2: [f32; 2],
 }
 
 impl CameraUniform {
     pub fn new() -> Self {
+        let identity = Mat4::IDENTITY.to_cols_array_2d();
         Self {
-            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            view_proj: identity,
+            view: identity,
+            proj: identity,
+            inv_view: identity,
+            inv_proj: identity,
+            inv_view_proj: identity,
+            eye: [0.0; 3],
+            _padding: 0.0,
+            viewport: [1.0; 2],
+            _padding2: [0.0; 2],
         }
     }
 
-    pub fn update_view_proj(&mut self, camera: &Camera) {
-        self.view_proj = camera.build_view_projection_matrix().to_cols_array_2d();
+    pub fn update(&mut self, camera: &Camera, width: u32, height: u32) {
+        let view = camera.build_view_matrix();
+        let proj = camera.build_projection_matrix();
+        let view_proj = proj * view;
+
+        self.view_proj = view_proj.to_cols_array_2d();
+        self.view = view.to_cols_array_2d();
+        self.proj = proj.to_cols_array_2d();
+        self.inv_view = view.inverse().to_cols_array_2d();
+        self.inv_proj = proj.inverse().to_cols_array_2d();
+        self.inv_view_proj = view_proj.inverse().to_cols_array_2d();
+        self.eye = camera.eye.to_array();
+        self.viewport = [width as f32, height as f32];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec4;
+
+    use super::*;
+
+    #[test]
+    fn reverse_z_maps_near_plane_to_one() {
+        let projection = Projection::InfiniteReverseZ {
+            fovy: 45.0,
+            znear: 0.1,
+        };
+        let proj = projection.build_projection_matrix(1.0);
+
+        let near = proj * Vec4::new(0.0, 0.0, -0.1, 1.0);
+        let far = proj * Vec4::new(0.0, 0.0, -1.0e6, 1.0);
+
+        assert!((near.z / near.w - 1.0).abs() < 1e-5);
+        assert!(far.z / far.w < 1e-5);
+        assert_eq!(projection.depth_compare(), wgpu::CompareFunction::Greater);
+    }
+
+    #[test]
+    fn orthographic_keeps_size_independent_of_distance() {
+        let projection = Projection::Orthographic {
+            height: 2.0,
+            znear: 0.1,
+            zfar: 100.0,
+        };
+        let proj = projection.build_projection_matrix(2.0);
+
+        let close = proj * Vec4::new(2.0, 1.0, -1.0, 1.0);
+        let distant = proj * Vec4::new(2.0, 1.0, -50.0, 1.0);
+
+        assert_eq!(close.x, distant.x);
+        assert!((close.x - 1.0).abs() < 1e-5);
+        assert!((close.y - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn uniform_layout_matches_wgsl() {
+        assert_eq!(std::mem::offset_of!(CameraUniform, eye), 384);
+        assert_eq!(std::mem::offset_of!(CameraUniform, viewport), 400);
+        assert_eq!(std::mem::size_of::<CameraUniform>(), 416);
+    }
+
+    #[test]
+    fn inverse_view_projection_recovers_world_position() {
+        let camera = Camera {
+            eye: Vec3::new(0.0, 1.0, 2.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect: 1.5,
+            projection: Projection::InfiniteReverseZ {
+                fovy: 45.0,
+                znear: 0.1,
+            },
+        };
+        let mut uniform = CameraUniform::new();
+        uniform.update(&camera, 300, 200);
+
+        let view_proj = Mat4::from_cols_array_2d(&uniform.view_proj);
+        let inv_view_proj = Mat4::from_cols_array_2d(&uniform.inv_view_proj);
+        let world = Vec3::new(0.3, -0.2, -0.5);
+        let clip = view_proj * world.extend(1.0);
+        let ndc = clip / clip.w;
+        let restored = inv_view_proj * ndc;
+
+        assert!((restored.truncate() / restored.w).abs_diff_eq(world, 1e-4));
+        assert_eq!(uniform.eye, [0.0, 1.0, 2.0]);
+        assert_eq!(uniform.viewport, [300.0, 200.0]);
     }
 }
@@ -0,0 +1,11 @@
+// 与 camera.rs 中的 CameraUniform 一一对应，绑定位置由引入它的着色器决定
+struct CameraUniform {
+    view_proj: mat4x4f,
+    view: mat4x4f,
+    proj: mat4x4f,
+    inv_view: mat4x4f,
+    inv_proj: mat4x4f,
+    inv_view_proj: mat4x4f,
+    eye: vec3f,
+    viewport: vec2f,
+}
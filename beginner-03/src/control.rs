@@ -1,74 +1,324 @@
+use std::time::Duration;
+
+use glam::{Vec2, Vec3};
 use winit::{
-    event::{ElementState, KeyEvent},
-    keyboard::{KeyCode, NamedKey},
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta},
+    keyboard::{KeyCode, PhysicalKey},
 };
 
 use crate::camera::Camera;
 
+/// 俯仰角限制在 ±89°，避免视线方向与 `up` 重合
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// 所有相机控制器的公共接口，`WgpuApp` 通过它在运行时切换控制器
+pub trait CameraController {
+    fn handle_keyboard_input(&mut self, _event: &KeyEvent) -> bool {
+        false
+    }
+
+    fn handle_mouse_button(&mut self, _state: ElementState, _button: MouseButton) -> bool {
+        false
+    }
+
+    /// `DeviceEvent::MouseMotion` 的原始位移
+    fn handle_mouse_motion(&mut self, _dx: f64, _dy: f64) {}
+
+    fn handle_mouse_wheel(&mut self, _delta: MouseScrollDelta) {}
+
+    /// 是否需要把鼠标锁定在窗口内
+    fn wants_cursor_grab(&self) -> bool {
+        false
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration);
+}
+
+/// 第一人称控制器：WASD 平移，Space / Shift 升降，鼠标控制朝向
 #[derive(Debug, Clone, Copy)]
 pub struct PlayerController {
+    /// 每秒移动的距离
     pub speed: f32,
+    /// 鼠标每移动一个像素转过的弧度
+    pub sensitivity: f32,
     pub forward: bool,
     pub left: bool,
     pub right: bool,
     pub backward: bool,
+    pub up: bool,
+    pub down: bool,
+    yaw_delta: f32,
+    pitch_delta: f32,
 }
 
 impl Default for PlayerController {
     fn default() -> Self {
         Self {
-            speed: 0.05,
+            speed: 3.0,
+            sensitivity: 0.002,
             forward: false,
             left: false,
             right: false,
             backward: false,
+            up: false,
+            down: false,
+            yaw_delta: 0.0,
+            pitch_delta: 0.0,
         }
     }
 }
 
-impl PlayerController {
-    pub fn handle_keyboard_input(&mut self, event: KeyEvent) {
-        match event {
-            KeyEvent {
-                physical_key,
-                logical_key,
-                state,
-                ..
-            } => {
-                let pressed = state == ElementState::Pressed;
-                match logical_key {
-                    winit::keyboard::Key::Named(NamedKey::Space) => todo!(),
-                    _ => {}
-                }
-                match physical_key {
-                    winit::keyboard::PhysicalKey::Code(KeyCode::KeyW) => {
-                        self.forward = pressed;
-                    }
-                    winit::keyboard::PhysicalKey::Code(KeyCode::KeyA) => {
-                        self.left = pressed;
-                    }
-                    winit::keyboard::PhysicalKey::Code(KeyCode::KeyS) => {
-                        self.backward = pressed;
-                    }
-                    winit::keyboard::PhysicalKey::Code(KeyCode::KeyD) => {
-                        self.right = pressed;
-                    }
-                    _ => {}
-                }
-            }
+impl CameraController for PlayerController {
+    fn handle_keyboard_input(&mut self, event: &KeyEvent) -> bool {
+        let pressed = event.state == ElementState::Pressed;
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return false;
         };
+
+        match code {
+            KeyCode::KeyW => self.forward = pressed,
+            KeyCode::KeyA => self.left = pressed,
+            KeyCode::KeyS => self.backward = pressed,
+            KeyCode::KeyD => self.right = pressed,
+            KeyCode::Space => self.up = pressed,
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => self.down = pressed,
+            _ => return false,
+        }
+        true
+    }
+
+    /// 累积鼠标位移，在下一次 `update_camera` 时应用
+    fn handle_mouse_motion(&mut self, dx: f64, dy: f64) {
+        self.yaw_delta += dx as f32;
+        self.pitch_delta += dy as f32;
+    }
+
+    fn wants_cursor_grab(&self) -> bool {
+        true
     }
 
-    pub fn update_camera(&mut self, camera: &mut Camera) {
-        let forward = camera.target - camera.eye;
-        let camera_norm = forward.normalize();
-        let forward_mag = forward.length();
+    fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        let offset = camera.target - camera.eye;
+        let distance = offset.length().max(f32::EPSILON);
+        let direction = offset / distance;
+
+        let yaw = direction.z.atan2(direction.x) + self.yaw_delta * self.sensitivity;
+        let pitch = (direction.y.clamp(-1.0, 1.0).asin() - self.pitch_delta * self.sensitivity)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw_delta = 0.0;
+        self.pitch_delta = 0.0;
 
-        if self.forward && forward_mag > self.speed {
-            camera.eye += camera_norm * self.speed;
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        let forward = Vec3::new(cos_yaw * cos_pitch, sin_pitch, sin_yaw * cos_pitch);
+
+        // 水平移动不受俯仰角影响
+        let flat_forward = Vec3::new(cos_yaw, 0.0, sin_yaw);
+        let right = flat_forward.cross(camera.up).normalize();
+
+        let mut movement = Vec3::ZERO;
+        if self.forward {
+            movement += flat_forward;
         }
         if self.backward {
-            camera.eye -= camera_norm * self.speed;
+            movement -= flat_forward;
+        }
+        if self.right {
+            movement += right;
+        }
+        if self.left {
+            movement -= right;
+        }
+        if self.up {
+            movement += camera.up;
         }
+        if self.down {
+            movement -= camera.up;
+        }
+
+        camera.eye += movement.normalize_or_zero() * self.speed * dt;
+        camera.target = camera.eye + forward * distance;
+    }
+}
+
+/// 观察模型用的轨道相机：左键拖动绕 `target` 旋转，滚轮缩放，中键拖动平移
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitController {
+    /// 鼠标每移动一个像素转过的弧度
+    pub rotate_sensitivity: f32,
+    /// 鼠标每移动一个像素平移的距离占相机到目标距离的比例
+    pub pan_sensitivity: f32,
+    /// 滚轮每滚动一格距离缩放的比例
+    pub zoom_step: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    rotating: bool,
+    panning: bool,
+    rotate_delta: Vec2,
+    pan_delta: Vec2,
+    zoom_delta: f32,
+}
+
+impl Default for OrbitController {
+    fn default() -> Self {
+        Self {
+            rotate_sensitivity: 0.005,
+            pan_sensitivity: 0.002,
+            zoom_step: 0.1,
+            min_distance: 0.2,
+            max_distance: 50.0,
+            rotating: false,
+            panning: false,
+            rotate_delta: Vec2::ZERO,
+            pan_delta: Vec2::ZERO,
+            zoom_delta: 0.0,
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn handle_mouse_button(&mut self, state: ElementState, button: MouseButton) -> bool {
+        let pressed = state == ElementState::Pressed;
+        match button {
+            MouseButton::Left => self.rotating = pressed,
+            MouseButton::Middle => self.panning = pressed,
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_mouse_motion(&mut self, dx: f64, dy: f64) {
+        let delta = Vec2::new(dx as f32, dy as f32);
+        if self.rotating {
+            self.rotate_delta += delta;
+        }
+        if self.panning {
+            self.pan_delta += delta;
+        }
+    }
+
+    fn handle_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        self.zoom_delta += match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            // 触控板按像素滚动，大约 50 像素算一格
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0,
+        };
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, _dt: Duration) {
+        let offset = camera.eye - camera.target;
+        let distance = offset.length().max(f32::EPSILON);
+
+        // 平移：沿屏幕的左右、上下方向同时移动相机和目标点
+        let forward = -offset / distance;
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
+        let pan =
+            (-right * self.pan_delta.x + up * self.pan_delta.y) * self.pan_sensitivity * distance;
+        camera.target += pan;
+
+        let azimuth = offset.z.atan2(offset.x) + self.rotate_delta.x * self.rotate_sensitivity;
+        let elevation = ((offset.y / distance).clamp(-1.0, 1.0).asin()
+            + self.rotate_delta.y * self.rotate_sensitivity)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+        let distance = (distance * (1.0 - self.zoom_step).powf(self.zoom_delta))
+            .clamp(self.min_distance, self.max_distance);
+
+        self.rotate_delta = Vec2::ZERO;
+        self.pan_delta = Vec2::ZERO;
+        self.zoom_delta = 0.0;
+
+        let (sin_azimuth, cos_azimuth) = azimuth.sin_cos();
+        let (sin_elevation, cos_elevation) = elevation.sin_cos();
+        camera.eye = camera.target
+            + Vec3::new(
+                cos_azimuth * cos_elevation,
+                sin_elevation,
+                sin_azimuth * cos_elevation,
+            ) * distance;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Projection;
+
+    fn camera() -> Camera {
+        Camera {
+            eye: Vec3::new(0.0, 0.0, 2.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect: 1.0,
+            projection: Projection::Perspective {
+                fovy: 45.0,
+                znear: 0.1,
+                zfar: 100.0,
+            },
+        }
+    }
+
+    #[test]
+    fn movement_is_frame_rate_independent() {
+        let mut controller = PlayerController {
+            right: true,
+            ..Default::default()
+        };
+
+        let mut one_step = camera();
+        controller.update_camera(&mut one_step, Duration::from_millis(100));
+
+        let mut ten_steps = camera();
+        for _ in 0..10 {
+            controller.update_camera(&mut ten_steps, Duration::from_millis(10));
+        }
+
+        assert!(one_step.eye.abs_diff_eq(ten_steps.eye, 1e-5));
+        assert!(one_step.eye.abs_diff_eq(Vec3::new(0.3, 0.0, 2.0), 1e-5));
+    }
+
+    #[test]
+    fn mouse_motion_rotates_without_moving() {
+        let mut controller = PlayerController::default();
+        let mut camera = camera();
+
+        controller.handle_mouse_motion(0.0, -1.0e6);
+        controller.update_camera(&mut camera, Duration::ZERO);
+
+        let direction = (camera.target - camera.eye).normalize();
+        assert_eq!(camera.eye, Vec3::new(0.0, 0.0, 2.0));
+        assert!((direction.y - MAX_PITCH.sin()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn orbit_rotation_keeps_distance_to_target() {
+        let mut controller = OrbitController::default();
+        let mut camera = camera();
+
+        controller.handle_mouse_button(ElementState::Pressed, MouseButton::Left);
+        controller.handle_mouse_motion(120.0, 40.0);
+        controller.update_camera(&mut camera, Duration::ZERO);
+
+        assert_eq!(camera.target, Vec3::ZERO);
+        assert!((camera.eye.length() - 2.0).abs() < 1e-5);
+        assert!(!camera.eye.abs_diff_eq(Vec3::new(0.0, 0.0, 2.0), 1e-3));
+    }
+
+    #[test]
+    fn orbit_zoom_and_pan() {
+        let mut controller = OrbitController::default();
+        let mut camera = camera();
+
+        controller.handle_mouse_wheel(MouseScrollDelta::LineDelta(0.0, 1.0));
+        controller.update_camera(&mut camera, Duration::ZERO);
+        assert!((camera.eye.length() - 1.8).abs() < 1e-5);
+
+        controller.handle_mouse_button(ElementState::Pressed, MouseButton::Middle);
+        controller.handle_mouse_motion(-100.0, 0.0);
+        controller.update_camera(&mut camera, Duration::ZERO);
+        assert!(camera.target.x > 0.0);
+        assert!(((camera.eye - camera.target).length() - 1.8).abs() < 1e-5);
     }
 }
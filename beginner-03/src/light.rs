@@ -0,0 +1,391 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use utils::{
+    hot_reload::{HotShader, capture_device_errors},
+    texture::Texture,
+};
+
+use crate::{
+    shaders,
+    shadow::{CASCADE_COUNT, MAX_SHADOW_LAYERS},
+};
+use wgpu::util::DeviceExt;
+
+/// 光源的类型，与着色器中的 `LIGHT_*` 常量对应
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// 向所有方向发光，不投射阴影
+    Point,
+    /// 平行光，忽略光源的位置，使用级联阴影
+    Directional { direction: Vec3 },
+    /// `angle` 为外圆锥的半角，单位为弧度
+    Spot { direction: Vec3, angle: f32 },
+}
+
+/// 聚光灯内圆锥与外圆锥半角的比值，两者之间的光照平滑衰减
+const SPOT_INNER_RATIO: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub kind: LightKind,
+}
+
+impl Light {
+    /// `shadow` 为阴影贴图中第一层的下标，没有阴影时为 `None`
+    pub fn to_uniform(self, shadow: Option<u32>) -> LightUniform {
+        let (kind, direction, cos_outer, cos_inner) = match self.kind {
+            LightKind::Point => (0, Vec3::ZERO, -1.0, -1.0),
+            LightKind::Directional { direction } => (1, direction.normalize(), -1.0, -1.0),
+            LightKind::Spot { direction, angle } => (
+                2,
+                direction.normalize(),
+                angle.cos(),
+                (angle * SPOT_INNER_RATIO).cos(),
+            ),
+        };
+        LightUniform {
+            position: self.position.to_array(),
+            intensity: self.intensity,
+            color: self.color.to_array(),
+            kind,
+            direction: direction.to_array(),
+            cos_outer,
+            shadow: shadow.map_or(-1, |layer| layer as i32),
+            cos_inner,
+            _padding: [0; 2],
+        }
+    }
+
+    /// 投射阴影需要的阴影贴图层数，方向光每一级级联占一层
+    pub fn shadow_layers(&self) -> u32 {
+        match self.kind {
+            LightKind::Point => 0,
+            LightKind::Directional { .. } => CASCADE_COUNT as u32,
+            LightKind::Spot { .. } => 1,
+        }
+    }
+}
+
+/// 依次为每个光源分配阴影贴图中的层，放不下的光源不投射阴影
+pub fn assign_shadow_layers(lights: &[Light]) -> Vec<Option<u32>> {
+    let mut next = 0;
+    lights
+        .iter()
+        .take(MAX_LIGHTS)
+        .map(|light| {
+            let layers = light.shadow_layers();
+            if layers == 0 || next + layers > MAX_SHADOW_LAYERS as u32 {
+                return None;
+            }
+            next += layers;
+            Some(next - layers)
+        })
+        .collect()
+}
+
+/// 与着色器中的 `Light` 对应，标量都填在前面 `vec3f` 后面的空位
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct LightUniform {
+    pub position: [f32; 3],
+    pub intensity: f32,
+    pub color: [f32; 3],
+    pub kind: u32,
+    pub direction: [f32; 3],
+    pub cos_outer: f32,
+    /// 阴影贴图中第一层的下标，-1 表示没有阴影
+    pub shadow: i32,
+    pub cos_inner: f32,
+    _padding: [u32; 2],
+}
+
+/// 着色器中 `Lights` 数组的长度
+pub const MAX_LIGHTS: usize = 4;
+
+/// 与着色器中的 `Lights` 对应，只有前 `count` 个光源有效
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct LightsUniform {
+    pub lights: [LightUniform; MAX_LIGHTS],
+    pub count: u32,
+    /// 环境光照的强度，0 表示不使用环境贴图
+    pub env_intensity: f32,
+    /// 环境贴图最粗糙的 mip 级别，完全粗糙的表面从这一级采样
+    pub env_max_lod: f32,
+    _padding: u32,
+}
+
+impl LightsUniform {
+    /// 超过 [`MAX_LIGHTS`] 的光源会被忽略，阴影贴图的层按 [`assign_shadow_layers`] 分配
+    pub fn new(lights: &[Light]) -> Self {
+        let mut uniform = Self::zeroed();
+        let shadows = assign_shadow_layers(lights);
+        for ((slot, light), shadow) in uniform.lights.iter_mut().zip(lights).zip(shadows) {
+            *slot = light.to_uniform(shadow);
+        }
+        uniform.count = lights.len().min(MAX_LIGHTS) as u32;
+        uniform
+    }
+
+    pub fn with_environment(self, intensity: f32, max_lod: f32) -> Self {
+        Self {
+            env_intensity: intensity,
+            env_max_lod: max_lod,
+            ..self
+        }
+    }
+}
+
+#[rustfmt::skip]
+const CUBE_VERTICES: &[[f32; 3]] = &[
+    [-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [-1.0, 1.0, -1.0],
+    [-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0],
+];
+
+#[rustfmt::skip]
+const CUBE_INDICES: &[u16] = &[
+    0, 2, 1, 0, 3, 2, // -Z
+    4, 5, 6, 4, 6, 7, // +Z
+    0, 1, 5, 0, 5, 4, // -Y
+    3, 7, 6, 3, 6, 2, // +Y
+    0, 4, 7, 0, 7, 3, // -X
+    1, 2, 6, 1, 6, 5, // +X
+];
+
+/// 在每个光源的位置绘制一个小立方体，颜色为光源颜色
+pub struct LightDebug {
+    shader: HotShader,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+}
+
+impl LightDebug {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        camera_layout: &wgpu::BindGroupLayout,
+        light_layout: &wgpu::BindGroupLayout,
+        depth_compare: wgpu::CompareFunction,
+        multisample: wgpu::MultisampleState,
+    ) -> Self {
+        let shader = utils::hot_shader!(
+            device,
+            "Light Debug Shader",
+            "light.wgsl",
+            &shaders::preprocessor()
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Light Debug Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, light_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = Self::create_pipeline(
+            device,
+            &pipeline_layout,
+            shader.module(),
+            format,
+            depth_compare,
+            multisample,
+        );
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Debug Vertex Buffer"),
+            contents: bytemuck::cast_slice(CUBE_VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Debug Index Buffer"),
+            contents: bytemuck::cast_slice(CUBE_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            shader,
+            pipeline_layout,
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        depth_compare: wgpu::CompareFunction,
+        multisample: wgpu::MultisampleState,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Light Debug Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                }],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample,
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    pub fn shader(&self) -> &HotShader {
+        &self.shader
+    }
+
+    pub fn shader_mut(&mut self) -> &mut HotShader {
+        &mut self.shader
+    }
+
+    /// 切换投影方式后深度比较函数可能改变，切换采样数后多重采样状态也会改变，
+    /// 着色器重新加载后也需要重新创建管线；创建失败时保留原来的管线
+    pub fn recreate_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_compare: wgpu::CompareFunction,
+        multisample: wgpu::MultisampleState,
+    ) {
+        match capture_device_errors(device, || {
+            Self::create_pipeline(
+                device,
+                &self.pipeline_layout,
+                self.shader.module(),
+                format,
+                depth_compare,
+                multisample,
+            )
+        }) {
+            Ok(pipeline) => self.pipeline = pipeline,
+            Err(e) => log::error!(
+                "Failed to create pipeline for {}: {e}",
+                self.shader.path().display()
+            ),
+        }
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+        light_bind_group: &'a wgpu::BindGroup,
+        light_count: u32,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, light_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        // 每个光源一个实例，着色器用 instance_index 读取光源
+        render_pass.draw_indexed(0..CUBE_INDICES.len() as u32, 0, 0..light_count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_layout_matches_wgsl() {
+        assert_eq!(std::mem::offset_of!(LightUniform, intensity), 12);
+        assert_eq!(std::mem::offset_of!(LightUniform, color), 16);
+        assert_eq!(std::mem::offset_of!(LightUniform, kind), 28);
+        assert_eq!(std::mem::offset_of!(LightUniform, direction), 32);
+        assert_eq!(std::mem::offset_of!(LightUniform, shadow), 48);
+        assert_eq!(std::mem::size_of::<LightUniform>(), 64);
+        assert_eq!(std::mem::offset_of!(LightsUniform, count), 256);
+        assert_eq!(std::mem::offset_of!(LightsUniform, env_intensity), 260);
+        assert_eq!(std::mem::offset_of!(LightsUniform, env_max_lod), 264);
+        assert_eq!(std::mem::size_of::<LightsUniform>(), 272);
+    }
+
+    #[test]
+    fn extra_lights_are_ignored() {
+        let light = Light {
+            position: Vec3::ONE,
+            color: Vec3::ONE,
+            intensity: 1.0,
+            kind: LightKind::Point,
+        };
+        let uniform = LightsUniform::new(&[light; MAX_LIGHTS + 2]);
+
+        assert_eq!(uniform.count, MAX_LIGHTS as u32);
+        assert_eq!(uniform.lights[MAX_LIGHTS - 1].position, [1.0; 3]);
+    }
+
+    #[test]
+    fn shadow_layers_are_packed_in_order() {
+        let light = |kind| Light {
+            position: Vec3::ZERO,
+            color: Vec3::ONE,
+            intensity: 1.0,
+            kind,
+        };
+        let sun = light(LightKind::Directional {
+            direction: Vec3::NEG_Y,
+        });
+        let spot = light(LightKind::Spot {
+            direction: Vec3::NEG_Y,
+            angle: 0.5,
+        });
+        let cascades = CASCADE_COUNT as u32;
+
+        assert_eq!(
+            assign_shadow_layers(&[light(LightKind::Point), sun, spot, sun]),
+            [None, Some(0), Some(cascades), Some(cascades + 1)]
+        );
+
+        let uniform = LightsUniform::new(&[spot, light(LightKind::Point)]);
+        assert_eq!(uniform.lights[0].shadow, 0);
+        assert_eq!(uniform.lights[1].shadow, -1);
+    }
+
+    #[test]
+    fn cube_faces_point_outwards() {
+        for triangle in CUBE_INDICES.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(CUBE_VERTICES[triangle[i] as usize]));
+            let normal = (b - a).cross(c - a);
+            let center = (a + b + c) / 3.0;
+            assert!(
+                normal.dot(center) > 0.0,
+                "triangle {triangle:?} faces inwards"
+            );
+        }
+    }
+}
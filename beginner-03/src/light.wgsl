@@ -0,0 +1,38 @@
+#include "camera.wgsl"
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+#include "lights.wgsl"
+
+@group(1) @binding(0)
+var<uniform> lights: Lights;
+
+// 立方体的半边长
+const SCALE: f32 = 0.05;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) color: vec3f,
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3f,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    let light = lights.lights[instance];
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4f(position * SCALE + light.position, 1.0);
+    // 方向光没有位置，放到裁剪空间之外
+    if light.kind == LIGHT_DIRECTIONAL {
+        out.clip_position = vec4f(0.0, 0.0, 2.0, 1.0);
+    }
+    out.color = light.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(in.color, 1.0);
+}
@@ -0,0 +1,100 @@
+// 光源和阴影的绑定以及采样函数，位于 @group(3)
+//
+// shadow_factor 需要引入它的着色器声明 camera 变量；
+// 定义 ENVIRONMENT 时还会声明环境贴图的绑定
+#include "camera.wgsl"
+#include "lights.wgsl"
+
+@group(3) @binding(0)
+var<uniform> lights: Lights;
+
+const CASCADE_COUNT: u32 = 3;
+const MAX_SHADOW_LAYERS: u32 = 8;
+
+struct Shadows {
+    view_proj: array<mat4x4f, MAX_SHADOW_LAYERS>,
+    // 每一级级联远端在相机视图空间中的深度
+    cascade_splits: vec4f,
+}
+@group(3) @binding(1)
+var<uniform> shadows: Shadows;
+@group(3) @binding(2)
+var shadow_map: texture_depth_2d_array;
+@group(3) @binding(3)
+var shadow_sampler: sampler_comparison;
+#ifdef ENVIRONMENT
+// 与天空盒相同的环境贴图，用于基于图像的光照
+@group(3) @binding(4)
+var t_environment: texture_cube<f32>;
+@group(3) @binding(5)
+var s_environment: sampler;
+#endif
+
+// 沿法线偏移阴影的采样位置，减少阴影痤疮
+const SHADOW_NORMAL_OFFSET: f32 = 0.01;
+
+struct LightSample {
+    // 指向光源的单位向量
+    direction: vec3f,
+    radiance: vec3f,
+}
+
+fn sample_light(light: Light, position: vec3f) -> LightSample {
+    var out: LightSample;
+    if light.kind == LIGHT_DIRECTIONAL {
+        out.direction = -light.direction;
+        out.radiance = light.color * light.intensity;
+        return out;
+    }
+
+    let to_light = light.position - position;
+    let distance = length(to_light);
+    out.direction = to_light / distance;
+    // 平滑的平方反比衰减，避免光源附近过亮
+    out.radiance = light.color * light.intensity / (1.0 + distance * distance);
+    if light.kind == LIGHT_SPOT {
+        out.radiance *= smoothstep(light.cos_outer, light.cos_inner, dot(-out.direction, light.direction));
+    }
+    return out;
+}
+
+// 被照亮的比例，0 表示完全处于阴影中；在 3x3 范围内做 PCF
+fn shadow_factor(light: Light, position: vec3f, normal: vec3f) -> f32 {
+    if light.shadow < 0 {
+        return 1.0;
+    }
+
+    var layer = light.shadow;
+    if light.kind == LIGHT_DIRECTIONAL {
+        let depth = -(camera.view * vec4f(position, 1.0)).z;
+        var cascade = CASCADE_COUNT;
+        for (var i = 0u; i < CASCADE_COUNT; i++) {
+            if depth < shadows.cascade_splits[i] {
+                cascade = i;
+                break;
+            }
+        }
+        // 超出阴影距离
+        if cascade == CASCADE_COUNT {
+            return 1.0;
+        }
+        layer += i32(cascade);
+    }
+
+    let clip = shadows.view_proj[layer] * vec4f(position + normal * SHADOW_NORMAL_OFFSET, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2f(0.5, -0.5) + 0.5;
+    if any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+
+    let texel = 1.0 / vec2f(textureDimensions(shadow_map));
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2f(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, layer, ndc.z);
+        }
+    }
+    return lit / 9.0;
+}
@@ -0,0 +1,26 @@
+// 与 light.rs 中的 LightsUniform 一一对应，绑定位置由引入它的着色器决定
+struct Light {
+    position: vec3f,
+    intensity: f32,
+    color: vec3f,
+    kind: u32,
+    direction: vec3f,
+    cos_outer: f32,
+    // 阴影贴图中第一层的下标，-1 表示没有阴影
+    shadow: i32,
+    cos_inner: f32,
+}
+
+const LIGHT_POINT: u32 = 0;
+const LIGHT_DIRECTIONAL: u32 = 1;
+const LIGHT_SPOT: u32 = 2;
+
+const MAX_LIGHTS: u32 = 4;
+
+struct Lights {
+    lights: array<Light, MAX_LIGHTS>,
+    count: u32,
+    // 环境光照的强度和环境贴图最粗糙的 mip 级别
+    env_intensity: f32,
+    env_max_lod: f32,
+}
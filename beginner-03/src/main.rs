@@ -1,269 +1,406 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
-use bytemuck::{Pod, Zeroable};
-use parking_lot::Mutex;
+use utils::{
+    cubemap::CubeTexture,
+    depth::DepthDebug,
+    framework::{WgpuAppAction, run},
+    hdr::HdrPipeline,
+    hot_reload::{HotShader, ShaderWatcher, capture_device_errors},
+    instance::{Instance, InstanceBuffer, InstanceRaw},
+    mipmap::MipmapGenerator,
+    model::{DrawModel, Material, Mesh, ModelBindings, ModelVertex, compute_tangents},
+    msaa::MsaaTarget,
+    pbr::{PbrFactors, PbrMaps, PbrMaterial},
+    post::{
+        Bloom, BloomParams, ColorGrading, ColorLut, Fxaa, Gamma, PostChain, Vignette,
+        VignetteParams,
+    },
+    scene::{DrawScene, MaterialLayouts, NodeTransforms, Scene, SceneError, SceneMaterial},
+    texture::{Texture, TextureError, TextureOptions},
+    uniform::UniformBuffer,
+};
 use wgpu::util::DeviceExt;
 use winit::{
-    application::ApplicationHandler,
     dpi::PhysicalSize,
-    event_loop,
-    window::{Window, WindowAttributes},
+    event::{DeviceEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta, TouchPhase},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{CursorGrabMode, Window},
 };
 
-use crate::camera::{Camera, CameraUniform};
+use crate::{
+    camera::{Camera, CameraUniform, Projection},
+    control::{CameraController, OrbitController, PlayerController},
+    light::{Light, LightDebug, LightKind, LightsUniform, MAX_LIGHTS},
+    shadow::ShadowPass,
+    skybox::Skybox,
+};
 
 mod camera;
 mod control;
-mod texture;
-
-fn main() {
-    let event_loop = event_loop::EventLoop::new().expect("Failed to create event loop");
-    let mut app = WgpuAppHandler::default();
-    event_loop
-        .run_app(&mut app)
-        .expect("Failed to run application");
-}
+mod light;
+mod shaders;
+mod shadow;
+mod skybox;
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
-pub struct Vertex {
-    position: [f32; 3],
-    tex_coords: [f32; 2],
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run::<WgpuApp>("Beginner-03")?;
+    Ok(())
 }
 
-const VERTICES: &[Vertex] = &[
-    Vertex {
+const VERTICES: &[ModelVertex] = &[
+    ModelVertex {
         position: [-0.0868241, 0.49240386, 0.0],
         tex_coords: [0.4131759, 0.00759614],
+        normal: [0.0, 0.0, 1.0],
+        tangent: ModelVertex::DEFAULT_TANGENT,
     }, // A
-    Vertex {
+    ModelVertex {
         position: [-0.49513406, 0.06958647, 0.0],
         tex_coords: [0.0048659444, 0.43041354],
+        normal: [0.0, 0.0, 1.0],
+        tangent: ModelVertex::DEFAULT_TANGENT,
     }, // B
-    Vertex {
+    ModelVertex {
         position: [-0.21918549, -0.44939706, 0.0],
         tex_coords: [0.28081453, 0.949397],
+        normal: [0.0, 0.0, 1.0],
+        tangent: ModelVertex::DEFAULT_TANGENT,
     }, // C
-    Vertex {
+    ModelVertex {
         position: [0.35966998, -0.3473291, 0.0],
         tex_coords: [0.85967, 0.84732914],
+        normal: [0.0, 0.0, 1.0],
+        tangent: ModelVertex::DEFAULT_TANGENT,
     }, // D
-    Vertex {
+    ModelVertex {
         position: [0.44147372, 0.2347359, 0.0],
         tex_coords: [0.9414737, 0.2652641],
+        normal: [0.0, 0.0, 1.0],
+        tangent: ModelVertex::DEFAULT_TANGENT,
     }, // E
 ];
 
 const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
 
+const ZNEAR: f32 = 0.1;
+const ZFAR: f32 = 100.0;
+
+/// 按 P 键依次切换的投影方式
+const PROJECTIONS: [Projection; 3] = [
+    Projection::Perspective {
+        fovy: 45.0,
+        znear: ZNEAR,
+        zfar: ZFAR,
+    },
+    Projection::Orthographic {
+        height: 2.0,
+        znear: ZNEAR,
+        zfar: ZFAR,
+    },
+    Projection::InfiniteReverseZ {
+        fovy: 45.0,
+        znear: ZNEAR,
+    },
+];
+
 struct WgpuApp {
-    window: Arc<Window>,
-    surface: wgpu::Surface<'static>,
+    /// 无头模式下没有窗口和窗口表面
+    window: Option<Arc<Window>>,
+    surface: Option<wgpu::Surface<'static>>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    /// 调试构建下修改 `shader.wgsl`、`pbr.wgsl` 或它们引入的文件后自动重新加载并重建管线，出错时保留原来的管线
+    shader: HotShader,
+    shader_watcher: ShaderWatcher,
+    pipeline_layout: wgpu::PipelineLayout,
     pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
-    diffuse_bind_group: wgpu::BindGroup,
+    /// 按 B 键在 Blinn-Phong 和 PBR 之间切换
+    use_pbr: bool,
+    pbr_shader: HotShader,
+    pbr_pipeline_layout: wgpu::PipelineLayout,
+    pbr_pipeline: wgpu::RenderPipeline,
+    diffuse_material: Material,
+    /// 五边形的 PBR 材质，使用与 `diffuse_material` 相同的纹理
+    pentagon_pbr: PbrMaterial,
+    material_layouts: MaterialLayouts,
+    /// 加载纹理和环境贴图时生成 mip 链，渲染管线按格式缓存
+    mipmaps: MipmapGenerator,
+    transform_layout: wgpu::BindGroupLayout,
+    /// 五边形使用的单位变换
+    identity_transform: NodeTransforms,
+    /// 五边形的实例，按 G 键在单个和网格之间切换
+    instances: InstanceBuffer,
+    show_grid: bool,
+    /// 场景中的节点只绘制一次，使用单个默认实例
+    single_instance: InstanceBuffer,
+    /// 通过命令行参数加载的 glTF 场景
+    scene: Option<(Scene, NodeTransforms)>,
     camera: camera::Camera,
-    camera_uniform: camera::CameraUniform,
-    camera_buffer: wgpu::Buffer,
-    camera_bind_group: wgpu::BindGroup,
-    controller: control::PlayerController,
+    projection_index: usize,
+    camera_uniform: UniformBuffer<CameraUniform>,
+    /// 按 C 键在第一人称和轨道相机之间切换
+    controllers: Vec<Box<dyn CameraController>>,
+    active_controller: usize,
+    /// 鼠标被锁定在窗口内时才把鼠标移动用于转动视角
+    cursor_grabbed: bool,
+    last_update: Instant,
+    depth_texture: Texture,
+    depth_debug: DepthDebug,
+    /// 按 Z 键切换，显示深度缓冲而不是场景；多重采样的深度纹理不能绑定，开启 MSAA 时不显示并打印警告
+    show_depth: bool,
+    /// 场景先渲染到 HDR 纹理，按 T 键切换色调映射算子，+ / - 调整曝光
+    hdr: HdrPipeline,
+    /// 色调映射之后的后处理链，数字键 1 到 5 分别开关泛光、FXAA、暗角、颜色分级和伽马校正
+    post: PostChain,
+    /// 场景渲染通道的多重采样颜色目标，按 M 键在支持的采样数之间切换
+    ///
+    /// 与教程中解析到交换链不同，这里解析到 HDR 纹理：色调映射和后处理都在解析后的单采样纹理上进行，
+    /// 交换链和后处理的纹理始终是单采样的
+    msaa: MsaaTarget,
+    /// 适配器对 HDR 格式和深度格式都支持的采样数
+    sample_counts: Vec<u32>,
+    /// 最多使用前 [`MAX_LIGHTS`] 个光源，方向键在水平面上移动第一个光源，PageUp / PageDown 上下移动
+    lights: Vec<Light>,
+    /// 按 L 键在 [`light_setup`] 的几种光源组合之间切换
+    light_setup_index: usize,
+    shadow_pass: ShadowPass,
+    /// 按 F 键显示或隐藏用于接收阴影的地面
+    show_ground: bool,
+    ground: Mesh,
+    ground_material: SceneMaterial,
+    /// 只使用其中的缓冲，光源绑定组还包含阴影和环境贴图
+    lights_uniform: UniformBuffer<LightsUniform>,
+    light_bind_group: wgpu::BindGroup,
+    light_debug: LightDebug,
+    /// 环境贴图，通过第二个命令行参数加载 `.hdr` 全景图或包含六个面的目录，默认使用程序生成的天空；
+    /// 按 K 键显示或隐藏天空盒，环境光照不受影响
+    skybox: Skybox,
+    show_skybox: bool,
+    env_intensity: f32,
     size: PhysicalSize<u32>,
     change: bool,
 }
 
 impl WgpuApp {
-    async fn new(window: Arc<Window>) -> Self {
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
+    fn create(
+        window: Option<Arc<Window>>,
+        surface: Option<wgpu::Surface<'static>>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+        sample_counts: Vec<u32>,
+        environment: Option<&std::path::Path>,
+    ) -> Self {
+        let mipmaps = MipmapGenerator::new(&device);
+        let diffuse_texture = Texture::from_bytes(
+            &device,
+            &queue,
+            include_bytes!("../../happy-tree.png"),
+            Some("Diffuse Texture"),
+            TextureOptions::mipmapped(&mipmaps),
+        )
+        .expect("Failed to load diffuse texture");
 
-        let surface = instance
-            .create_surface(window.clone())
-            .expect("Failed to create surface");
+        let material_layouts = MaterialLayouts::new(&device);
+        let pentagon_pbr = PbrMaterial::new(
+            &device,
+            &queue,
+            &material_layouts.pbr,
+            "Pentagon PBR",
+            PbrFactors {
+                metallic: 0.0,
+                roughness: 0.5,
+                ..Default::default()
+            },
+            PbrMaps {
+                base_color: Some(diffuse_texture.clone()),
+                normal: Some(
+                    Texture::from_rgba(
+                        &device,
+                        &queue,
+                        &ripple_normal_map(RIPPLE_SIZE),
+                        RIPPLE_SIZE,
+                        RIPPLE_SIZE,
+                        Some("Ripple Normal Map"),
+                        TextureOptions::linear_mipmapped(&mipmaps),
+                    )
+                    .expect("Failed to create normal map"),
+                ),
+                ..Default::default()
+            },
+        )
+        .expect("Failed to create PBR material");
+        let diffuse_material =
+            Material::new(&device, "Diffuse", diffuse_texture, &material_layouts.basic);
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .expect("Failed to find an appropriate adapter");
+        let transform_layout = NodeTransforms::bind_group_layout(&device);
+        let identity_transform =
+            NodeTransforms::new(&device, &queue, &transform_layout, &[glam::Mat4::IDENTITY]);
 
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: Some("Device and Queue"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::defaults(),
-                memory_hints: wgpu::MemoryHints::default(),
-                trace: wgpu::Trace::Off,
-            })
-            .await
-            .expect("Failed to create device and queue");
+        let camera = Camera {
+            eye: (0.0, 1.0, 2.0).into(),
+            target: (0.0, 0.0, 0.0).into(),
+            up: glam::Vec3::Y,
+            aspect: config.width as f32 / config.height as f32,
+            projection: PROJECTIONS[0],
+        };
 
-        let size = window.inner_size();
-        let width = size.width.max(1);
-        let height = size.height.max(1);
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update(&camera, config.width, config.height);
 
-        let caps = surface.get_capabilities(&adapter);
+        let camera_uniform =
+            UniformBuffer::new(&device, "Camera", CAMERA_VISIBILITY, camera_uniform);
+        let camera_bind_group_layout = camera_uniform.layout();
 
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: caps.formats[0],
-            width,
-            height,
-            present_mode: wgpu::PresentMode::Fifo,
-            desired_maximum_frame_latency: 2,
-            alpha_mode: caps.alpha_modes[0],
-            view_formats: vec![],
-        };
+        let msaa = MsaaTarget::new(&device, &config, HdrPipeline::FORMAT, 1);
 
-        surface.configure(&device, &config);
+        let environment = environment
+            .and_then(|path| {
+                load_environment(&device, &queue, &mipmaps, path)
+                    .inspect_err(|e| log::error!("Failed to load environment {path:?}: {e}"))
+                    .ok()
+            })
+            .unwrap_or_else(|| {
+                CubeTexture::from_fn(&device, &queue, &mipmaps, skybox::SKY_SIZE, |dir| {
+                    skybox::procedural_sky(dir, -SUN_DIRECTION)
+                })
+            });
+        let skybox = Skybox::new(
+            &device,
+            HdrPipeline::FORMAT,
+            camera_bind_group_layout,
+            environment,
+            msaa.multisample_state(),
+        );
+
+        let lights = light_setup(0);
 
-        let (diffuse_texture_view, diffuse_sampler) = texture::new(&device, &queue);
+        let lights_uniform = UniformBuffer::new(
+            &device,
+            "Lights",
+            LIGHTS_VISIBILITY,
+            LightsUniform::new(&lights).with_environment(ENV_INTENSITY, skybox.max_lod()),
+        );
 
-        let texture_bind_group_layout =
+        let light_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Texture Bind Group Layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
+                label: Some("Light Bind Group Layout"),
+                entries: &light_layout_entries(),
             });
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Diffuse Bind Group"),
-            layout: &texture_bind_group_layout,
+        let mut shadow_pass = ShadowPass::new(
+            &device,
+            &material_layouts.basic,
+            camera_bind_group_layout,
+            &transform_layout,
+        );
+        shadow_pass.update(&queue, &lights, &camera);
+
+        let [shadow_uniform, shadow_map, shadow_sampler] = shadow_pass.bind_group_entries();
+        let [environment_map, environment_sampler] = skybox.bind_group_entries(4);
+        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Light Bind Group"),
+            layout: &light_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
+                    resource: lights_uniform.buffer().as_entire_binding(),
                 },
+                shadow_uniform,
+                shadow_map,
+                shadow_sampler,
+                environment_map,
+                environment_sampler,
             ],
         });
 
-        let camera = Camera {
-            eye: (0.0, 1.0, 2.0).into(),
-            target: (0.0, 0.0, 0.0).into(),
-            up: glam::Vec3::Y,
-            aspect: config.width as f32 / config.height as f32,
-            fovy: 45.0,
-            znear: 0.1,
-            zfar: 100.0,
-        };
+        let (ground, ground_material) = create_ground(&device, &queue, &material_layouts);
 
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&camera);
-
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let camera_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Camera Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            });
+        let light_debug = LightDebug::new(
+            &device,
+            HdrPipeline::FORMAT,
+            camera_bind_group_layout,
+            &light_bind_group_layout,
+            camera.projection.depth_compare(),
+            msaa.multisample_state(),
+        );
 
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Camera Bind Group"),
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-        });
-
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
+        let preprocessor = shaders::preprocessor();
+        let shader = utils::hot_shader!(&device, "Shader", "shader.wgsl", &preprocessor);
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Pipeline Layout"),
-            bind_group_layouts: &[&texture_bind_group_layout, &camera_bind_group_layout],
+            bind_group_layouts: &[
+                &material_layouts.basic,
+                camera_bind_group_layout,
+                &transform_layout,
+                &light_bind_group_layout,
+            ],
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                compilation_options: Default::default(),
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![
-                        0 => Float32x3,
-                        1 => Float32x2,
-                    ],
-                }],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                compilation_options: Default::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-            cache: None,
+        let pbr_shader = utils::hot_shader!(&device, "PBR Shader", "pbr.wgsl", &preprocessor);
+
+        let mut shader_watcher = ShaderWatcher::new();
+        for hot_shader in [
+            &shader,
+            &pbr_shader,
+            light_debug.shader(),
+            skybox.shader(),
+            shadow_pass.shader(),
+        ] {
+            for path in hot_shader.dependencies() {
+                shader_watcher.watch(path);
+            }
+        }
+
+        let pbr_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("PBR Pipeline Layout"),
+            bind_group_layouts: &[
+                &material_layouts.pbr,
+                camera_bind_group_layout,
+                &transform_layout,
+                &light_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
         });
 
+        let pbr_pipeline = create_pipeline(
+            &device,
+            &pbr_pipeline_layout,
+            pbr_shader.module(),
+            HdrPipeline::FORMAT,
+            camera.projection.depth_compare(),
+            msaa.multisample_state(),
+        );
+
+        let pipeline = create_pipeline(
+            &device,
+            &pipeline_layout,
+            shader.module(),
+            HdrPipeline::FORMAT,
+            camera.projection.depth_compare(),
+            msaa.multisample_state(),
+        );
+
+        let depth_texture = Texture::create_multisampled_depth_texture(
+            &device,
+            &config,
+            msaa.sample_count(),
+            Some("Depth Texture"),
+        );
+        let depth_debug = DepthDebug::new(
+            &device,
+            config.format,
+            &depth_texture,
+            camera.projection.depth_mapping(),
+        );
+        let hdr = HdrPipeline::new(&device, &config);
+        let post = create_post_chain(&device, &queue, &config);
+
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
             contents: bytemuck::cast_slice(VERTICES),
@@ -276,27 +413,732 @@ impl WgpuApp {
             usage: wgpu::BufferUsages::INDEX,
         });
 
+        let instances = InstanceBuffer::new(&device, &[Instance::default()]);
+        let single_instance = InstanceBuffer::new(&device, &[Instance::default()]);
+
         Self {
             window,
             surface,
             device,
             queue,
-            config,
+            shader,
+            shader_watcher,
+            pipeline_layout,
             pipeline,
             vertex_buffer,
             index_buffer,
-            diffuse_bind_group,
+            use_pbr: false,
+            pbr_shader,
+            pbr_pipeline_layout,
+            pbr_pipeline,
+            diffuse_material,
+            pentagon_pbr,
+            material_layouts,
+            mipmaps,
+            transform_layout,
+            identity_transform,
+            instances,
+            show_grid: false,
+            single_instance,
+            scene: None,
             camera,
+            projection_index: 0,
             camera_uniform,
-            camera_buffer,
-            camera_bind_group,
-            controller: control::PlayerController::default(),
-            size: PhysicalSize::new(width, height),
+            controllers: vec![
+                Box::new(PlayerController::default()),
+                Box::new(OrbitController::default()),
+            ],
+            active_controller: 0,
+            cursor_grabbed: false,
+            last_update: Instant::now(),
+            depth_texture,
+            depth_debug,
+            show_depth: false,
+            hdr,
+            post,
+            msaa,
+            sample_counts,
+            lights,
+            light_setup_index: 0,
+            shadow_pass,
+            show_ground: false,
+            ground,
+            ground_material,
+            lights_uniform,
+            light_bind_group,
+            light_debug,
+            skybox,
+            show_skybox: true,
+            env_intensity: ENV_INTENSITY,
+            size: PhysicalSize::new(config.width, config.height),
+            config,
             change: false,
         }
     }
 
-    fn set_size(&mut self, size: PhysicalSize<u32>) {
+    fn resize(&mut self) {
+        if self.change {
+            self.change = false;
+            self.config.width = self.size.width;
+            self.config.height = self.size.height;
+            if let Some(surface) = self.surface.as_ref() {
+                surface.configure(&self.device, &self.config);
+            }
+            self.camera.aspect = self.config.width as f32 / self.config.height as f32;
+
+            self.recreate_depth_texture();
+            self.msaa.resize(&self.device, &self.config);
+            self.hdr.resize(&self.device, &self.config);
+            self.post.resize(&self.device, &self.config);
+        }
+    }
+
+    fn recreate_depth_texture(&mut self) {
+        self.depth_texture = Texture::create_multisampled_depth_texture(
+            &self.device,
+            &self.config,
+            self.msaa.sample_count(),
+            Some("Depth Texture"),
+        );
+        // 多重采样的深度纹理不能绑定到着色器，深度视图只在关闭 MSAA 时可用
+        if self.msaa.sample_count() == 1 {
+            self.depth_debug.resize(&self.device, &self.depth_texture);
+        }
+    }
+
+    fn load_scene(&mut self, path: impl AsRef<std::path::Path>) -> Result<(), SceneError> {
+        let scene = Scene::load_gltf(
+            &self.device,
+            &self.queue,
+            &self.mipmaps,
+            path,
+            &self.material_layouts,
+        )?;
+        let transforms = NodeTransforms::new(
+            &self.device,
+            &self.queue,
+            &self.transform_layout,
+            &scene.world_transforms(),
+        );
+        self.scene = Some((scene, transforms));
+        Ok(())
+    }
+
+    fn set_instances(&mut self, instances: &[Instance]) {
+        self.instances.update(&self.device, &self.queue, instances);
+    }
+
+    fn set_projection(&mut self, projection: Projection) {
+        let depth_compare = projection.depth_compare();
+        let rebuild = depth_compare != self.camera.projection.depth_compare();
+        self.camera.projection = projection;
+        self.depth_debug
+            .set_mapping(&self.queue, projection.depth_mapping());
+
+        // 反向 Z 需要不同的深度比较函数，只能重新创建管线
+        if rebuild {
+            self.recreate_pipelines();
+        }
+    }
+
+    /// 切换采样数，颜色目标、深度纹理和场景的管线都要重新创建
+    fn set_sample_count(&mut self, sample_count: u32) {
+        if !self.sample_counts.contains(&sample_count) {
+            log::warn!("{sample_count}x MSAA is not supported");
+            return;
+        }
+        self.msaa
+            .set_sample_count(&self.device, &self.config, sample_count);
+        self.recreate_depth_texture();
+        self.recreate_pipelines();
+        if self.show_depth {
+            self.warn_depth_view_unavailable();
+        }
+    }
+
+    /// 深度视图只能显示单采样的深度纹理，开启 MSAA 时提示用户而不是静默地不显示
+    fn warn_depth_view_unavailable(&self) {
+        if self.msaa.sample_count() > 1 {
+            log::warn!(
+                "The depth view is unavailable with {}x MSAA; press M to turn MSAA off",
+                self.msaa.sample_count()
+            );
+        }
+    }
+
+    fn recreate_pipelines(&mut self) {
+        let depth_compare = self.camera.projection.depth_compare();
+        let multisample = self.msaa.multisample_state();
+        let pipelines = [
+            (&mut self.pipeline, &self.pipeline_layout, &self.shader),
+            (
+                &mut self.pbr_pipeline,
+                &self.pbr_pipeline_layout,
+                &self.pbr_shader,
+            ),
+        ];
+        for (pipeline, layout, shader) in pipelines {
+            // 重新加载的着色器可能与管线布局不匹配，此时保留原来的管线
+            match capture_device_errors(&self.device, || {
+                create_pipeline(
+                    &self.device,
+                    layout,
+                    shader.module(),
+                    HdrPipeline::FORMAT,
+                    depth_compare,
+                    multisample,
+                )
+            }) {
+                Ok(new) => *pipeline = new,
+                Err(e) => log::error!(
+                    "Failed to create pipeline for {}: {e}",
+                    shader.path().display()
+                ),
+            }
+        }
+        self.light_debug.recreate_pipeline(
+            &self.device,
+            HdrPipeline::FORMAT,
+            depth_compare,
+            multisample,
+        );
+        self.skybox
+            .recreate_pipeline(&self.device, HdrPipeline::FORMAT, multisample);
+        self.shadow_pass.recreate_pipeline(&self.device);
+    }
+
+    /// 所有可以热重载的着色器，[`WgpuApp::recreate_pipelines`] 会重建依赖它们的管线
+    fn hot_shaders_mut(&mut self) -> [&mut HotShader; 5] {
+        [
+            &mut self.shader,
+            &mut self.pbr_shader,
+            self.light_debug.shader_mut(),
+            self.skybox.shader_mut(),
+            self.shadow_pass.shader_mut(),
+        ]
+    }
+
+    /// 重新加载磁盘上变化了的着色器，有着色器更新时重建管线
+    fn reload_shaders(&mut self) {
+        let changed = self.shader_watcher.changed();
+        if changed.is_empty() {
+            return;
+        }
+
+        // `wgpu::Device` 是引用计数的句柄，克隆后才能同时借用着色器
+        let device = self.device.clone();
+        let mut watch = Vec::new();
+        for shader in self.hot_shaders_mut() {
+            if !shader
+                .dependencies()
+                .iter()
+                .any(|path| changed.contains(path))
+            {
+                continue;
+            }
+            match shader.reload(&device) {
+                Ok(()) => {
+                    log::info!("Reloaded {}", shader.path().display());
+                    watch.push(shader.dependencies().to_vec());
+                }
+                Err(e) => log::error!("Keeping the previous {}: {e}", shader.path().display()),
+            }
+        }
+        if !watch.is_empty() {
+            // 新引入的文件也需要监视
+            for path in watch.iter().flatten() {
+                self.shader_watcher.watch(path);
+            }
+            self.recreate_pipelines();
+        }
+    }
+
+    /// 法线贴图的强度在 0 和 1 之间切换
+    fn toggle_normal_map(&mut self) {
+        let factors = &mut self.pentagon_pbr.factors;
+        factors.normal_scale = 1.0 - factors.normal_scale;
+        self.pentagon_pbr.update(&self.queue);
+    }
+
+    /// 绘制五边形、地面和场景，设置 `@group(0)` 到 `@group(2)`；阴影通道也使用这里的绘制代码
+    fn draw_geometry<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+        pbr: bool,
+    ) {
+        let material = |material: &'a SceneMaterial| {
+            if pbr {
+                &material.pbr.bind_group
+            } else {
+                &material.material.bind_group
+            }
+        };
+        let pentagon_material = if pbr {
+            &self.pentagon_pbr.bind_group
+        } else {
+            &self.diffuse_material.bind_group
+        };
+
+        render_pass.set_bind_group(0, pentagon_material, &[]);
+        render_pass.set_bind_group(1, camera_bind_group, &[]);
+        render_pass.set_bind_group(2, self.identity_transform.bind_group(), &[0]);
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instances.slice());
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..INDICES.len() as u32, 0, 0..self.instances.len());
+
+        if self.show_ground {
+            render_pass.draw_mesh(
+                &self.ground,
+                material(&self.ground_material),
+                &ModelBindings {
+                    bind_groups: &[
+                        (camera_bind_group, &[]),
+                        (self.identity_transform.bind_group(), &[0]),
+                    ],
+                    instances: Some(self.single_instance.slice()),
+                },
+            );
+        }
+
+        if let Some((scene, transforms)) = &self.scene {
+            render_pass.set_vertex_buffer(1, self.single_instance.slice());
+            if pbr {
+                render_pass.draw_scene_pbr(scene, transforms, camera_bind_group);
+            } else {
+                render_pass.draw_scene(scene, transforms, camera_bind_group);
+            }
+        }
+    }
+
+    fn controller(&mut self) -> &mut dyn CameraController {
+        self.controllers[self.active_controller].as_mut()
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) {
+        let Some(window) = self.window.as_ref() else {
+            return;
+        };
+
+        if grab {
+            // 部分平台不支持 Locked，退而求其次使用 Confined
+            let result = window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+            if let Err(e) = result {
+                log::warn!("Failed to grab cursor: {e}");
+                return;
+            }
+        } else {
+            let _ = window.set_cursor_grab(CursorGrabMode::None);
+        }
+
+        window.set_cursor_visible(!grab);
+        self.cursor_grabbed = grab;
+    }
+}
+
+/// 每次按键移动光源的距离
+const LIGHT_STEP: f32 = 0.1;
+
+fn light_offset(key: PhysicalKey) -> Option<glam::Vec3> {
+    let PhysicalKey::Code(code) = key else {
+        return None;
+    };
+    match code {
+        KeyCode::ArrowLeft => Some(glam::Vec3::NEG_X),
+        KeyCode::ArrowRight => Some(glam::Vec3::X),
+        KeyCode::ArrowUp => Some(glam::Vec3::NEG_Z),
+        KeyCode::ArrowDown => Some(glam::Vec3::Z),
+        KeyCode::PageUp => Some(glam::Vec3::Y),
+        KeyCode::PageDown => Some(glam::Vec3::NEG_Y),
+        _ => None,
+    }
+}
+
+/// 每次按键调整的曝光，单位为 EV
+const EXPOSURE_STEP: f32 = 0.5;
+
+fn exposure_step(key: PhysicalKey) -> Option<f32> {
+    let PhysicalKey::Code(code) = key else {
+        return None;
+    };
+    match code {
+        KeyCode::Equal | KeyCode::NumpadAdd => Some(EXPOSURE_STEP),
+        KeyCode::Minus | KeyCode::NumpadSubtract => Some(-EXPOSURE_STEP),
+        _ => None,
+    }
+}
+
+/// 平行光的方向，程序生成的天空中的太阳也在这个方向上
+const SUN_DIRECTION: glam::Vec3 = glam::Vec3::new(-0.4, -1.0, -0.6);
+
+/// 按 L 键依次切换的光源组合数量
+const LIGHT_SETUPS: usize = 3;
+
+/// 第 `index` 种光源组合：点光源；带级联阴影的平行光；平行光加上带阴影的聚光灯
+fn light_setup(index: usize) -> Vec<Light> {
+    let point = Light {
+        position: glam::Vec3::new(1.0, 1.0, 1.5),
+        color: glam::Vec3::ONE,
+        intensity: 5.0,
+        kind: LightKind::Point,
+    };
+    let sun = Light {
+        position: glam::Vec3::ZERO,
+        color: glam::Vec3::new(1.0, 0.95, 0.85),
+        intensity: 0.8,
+        kind: LightKind::Directional {
+            direction: SUN_DIRECTION,
+        },
+    };
+    let spot_position = glam::Vec3::new(0.8, 1.0, 1.0);
+    let spot = Light {
+        position: spot_position,
+        color: glam::Vec3::new(0.6, 0.8, 1.0),
+        intensity: 4.0,
+        kind: LightKind::Spot {
+            direction: glam::Vec3::new(0.0, -0.2, 0.0) - spot_position,
+            angle: 0.5,
+        },
+    };
+
+    match index {
+        0 => vec![point],
+        1 => vec![sun],
+        _ => vec![sun, spot],
+    }
+}
+
+/// 地面的半边长
+const GROUND_SIZE: f32 = 3.0;
+/// 地面的高度，略低于五边形的最低点
+const GROUND_HEIGHT: f32 = -0.5;
+
+/// 朝上的正方形地面，以及它的灰色材质
+fn create_ground(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layouts: &MaterialLayouts,
+) -> (Mesh, SceneMaterial) {
+    let vertex = |x: f32, z: f32| ModelVertex {
+        position: [x * GROUND_SIZE, GROUND_HEIGHT, z * GROUND_SIZE],
+        tex_coords: [x * 0.5 + 0.5, z * 0.5 + 0.5],
+        normal: [0.0, 1.0, 0.0],
+        tangent: ModelVertex::DEFAULT_TANGENT,
+    };
+    let mut vertices = vec![
+        vertex(-1.0, 1.0),
+        vertex(1.0, 1.0),
+        vertex(1.0, -1.0),
+        vertex(-1.0, -1.0),
+    ];
+    let mut indices = [0, 1, 2, 0, 2, 3];
+    compute_tangents(&mut vertices, &mut indices);
+    let mesh = Mesh::new(device, "Ground", &vertices, &indices, 0);
+
+    let color = [0.8, 0.8, 0.8, 1.0];
+    let texture = Texture::from_rgba(
+        device,
+        queue,
+        &color.map(|c| (c * 255.0) as u8),
+        1,
+        1,
+        Some("Ground"),
+        TextureOptions::default(),
+    )
+    .expect("Failed to create ground texture");
+    let material = SceneMaterial {
+        material: Material::new(device, "Ground", texture, &layouts.basic),
+        pbr: PbrMaterial::new(
+            device,
+            queue,
+            &layouts.pbr,
+            "Ground",
+            PbrFactors {
+                base_color: color,
+                metallic: 0.0,
+                roughness: 0.9,
+                ..Default::default()
+            },
+            PbrMaps::default(),
+        )
+        .expect("Failed to create ground material"),
+    };
+    (mesh, material)
+}
+
+/// 泛光、FXAA、暗角、颜色分级和伽马校正，默认全部关闭
+fn create_post_chain(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    config: &wgpu::SurfaceConfiguration,
+) -> PostChain {
+    let format = config.format;
+    let lut = ColorLut::from_fn(device, queue, POST_LUT_SIZE, warm_grade);
+    let mut post = PostChain::new(device, config)
+        .with(Bloom::new(device, config, format, BloomParams::default()))
+        .with(Fxaa::new(device, format))
+        .with(Vignette::new(device, format, VignetteParams::default()))
+        .with(ColorGrading::new(device, format, lut, 1.0))
+        .with(Gamma::new(device, format, 1.2));
+    post.set_all_enabled(false);
+    post
+}
+
+const POST_LUT_SIZE: u32 = 16;
+
+/// 偏暖并稍微提高对比度的颜色分级
+fn warm_grade(color: glam::Vec3) -> glam::Vec3 {
+    let warm = color * glam::Vec3::new(1.08, 1.0, 0.88);
+    // 以 0.5 为中心的 S 形曲线
+    let contrast = warm * warm * (3.0 - 2.0 * warm);
+    warm.lerp(contrast, 0.3)
+}
+
+fn post_effect_index(key: PhysicalKey) -> Option<usize> {
+    let PhysicalKey::Code(code) = key else {
+        return None;
+    };
+    match code {
+        KeyCode::Digit1 => Some(0),
+        KeyCode::Digit2 => Some(1),
+        KeyCode::Digit3 => Some(2),
+        KeyCode::Digit4 => Some(3),
+        KeyCode::Digit5 => Some(4),
+        _ => None,
+    }
+}
+
+/// 五边形 PBR 材质使用的法线贴图的边长
+const RIPPLE_SIZE: u32 = 256;
+
+/// 从中心向外扩散的同心波纹，编码为切线空间法线，Y 轴指向图片上方
+fn ripple_normal_map(size: u32) -> Vec<u8> {
+    const FREQUENCY: f32 = 40.0;
+    const AMPLITUDE: f32 = 0.02;
+
+    (0..size * size)
+        .flat_map(|i| {
+            let x = (i % size) as f32 / size as f32 - 0.5;
+            let y = (i / size) as f32 / size as f32 - 0.5;
+            let r = (x * x + y * y).sqrt().max(1e-6);
+            // 高度 h = sin(r * FREQUENCY) * AMPLITUDE 在图片坐标中的梯度
+            let slope = (r * FREQUENCY).cos() * FREQUENCY * AMPLITUDE / r;
+            // 图片的 y 轴向下，切线空间的 Y 轴向上
+            let normal = glam::Vec3::new(-slope * x, slope * y, 1.0).normalize();
+            let [r, g, b] = (normal * 0.5 + 0.5)
+                .to_array()
+                .map(|c| (c * 255.0).round() as u8);
+            [r, g, b, 255]
+        })
+        .collect()
+}
+
+/// 64 x 64 个五边形，按位置旋转并着色
+fn instance_grid() -> Vec<Instance> {
+    utils::instance::grid(64, 64, 1.2)
+        .into_iter()
+        .map(|instance| {
+            let p = instance.position;
+            Instance {
+                rotation: glam::Quat::from_rotation_z(p.x * 0.1 + p.y * 0.05),
+                tint: [0.5 + p.x / 80.0, 0.5 + p.y / 80.0, 1.0, 1.0],
+                ..instance
+            }
+        })
+        .collect()
+}
+
+/// 环境光照的默认强度
+const ENV_INTENSITY: f32 = 0.5;
+
+/// `path` 为目录时读取其中的 `px.png` 等六个面，否则作为等距柱状投影的全景图读取
+fn load_environment(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mipmaps: &MipmapGenerator,
+    path: &std::path::Path,
+) -> Result<CubeTexture, TextureError> {
+    if path.is_dir() {
+        CubeTexture::from_face_paths(device, queue, mipmaps, path, "png")
+    } else {
+        CubeTexture::from_equirect_path(device, queue, mipmaps, path)
+    }
+}
+
+fn scene_sample_counts(adapter: &wgpu::Adapter, device: &wgpu::Device) -> Vec<u32> {
+    let counts = utils::msaa::supported_sample_counts(
+        adapter,
+        device,
+        &[HdrPipeline::FORMAT, Texture::DEPTH_FORMAT],
+    );
+    log::info!("Supported MSAA sample counts: {counts:?}");
+    counts
+}
+
+/// 相机绑定组 `@binding(0)` 的 [`CameraUniform`] 在顶点和片元着色器中都会用到
+const CAMERA_VISIBILITY: wgpu::ShaderStages =
+    wgpu::ShaderStages::VERTEX.union(wgpu::ShaderStages::FRAGMENT);
+
+/// [`LightsUniform`] 在顶点和片元着色器中都会用到
+const LIGHTS_VISIBILITY: wgpu::ShaderStages =
+    wgpu::ShaderStages::VERTEX.union(wgpu::ShaderStages::FRAGMENT);
+
+/// 光源绑定组：`@binding(0)` 为 [`LightsUniform`]，`@binding(1)` 到 `@binding(3)` 为阴影，
+/// `@binding(4)` 和 `@binding(5)` 为环境贴图
+///
+/// 阴影贴图、环境贴图和光源放在同一个绑定组中，主管线的 4 个绑定组已经用满
+fn light_layout_entries() -> [wgpu::BindGroupLayoutEntry; 6] {
+    let [shadow_uniform, shadow_map, shadow_sampler] = ShadowPass::bind_group_layout_entries();
+    let [environment, environment_sampler] = Skybox::environment_layout_entries(4);
+    let [lights] = UniformBuffer::<LightsUniform>::bind_group_layout_entries(LIGHTS_VISIBILITY);
+    [
+        lights,
+        shadow_uniform,
+        shadow_map,
+        shadow_sampler,
+        environment,
+        environment_sampler,
+    ]
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    depth_compare: wgpu::CompareFunction,
+    multisample: wgpu::MultisampleState,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[ModelVertex::LAYOUT, InstanceRaw::LAYOUT],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample,
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+impl WgpuAppAction for WgpuApp {
+    async fn new(window: Arc<Window>) -> Self {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        let surface = instance
+            .create_surface(window.clone())
+            .expect("Failed to create surface");
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .expect("Failed to find an appropriate adapter");
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("Device and Queue"),
+                // 开启后才能使用适配器支持的所有采样数，否则只有 1 和 4
+                required_features: adapter.features()
+                    & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                required_limits: wgpu::Limits::defaults(),
+                memory_hints: wgpu::MemoryHints::default(),
+                trace: wgpu::Trace::Off,
+            })
+            .await
+            .expect("Failed to create device and queue");
+
+        let size = window.inner_size();
+        let width = size.width.max(1);
+        let height = size.height.max(1);
+
+        let caps = surface.get_capabilities(&adapter);
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: caps.formats[0],
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+
+        surface.configure(&device, &config);
+
+        let sample_counts = scene_sample_counts(&adapter, &device);
+        let environment = std::env::args().nth(2);
+        let mut app = Self::create(
+            Some(window),
+            Some(surface),
+            device,
+            queue,
+            config,
+            sample_counts,
+            environment.as_deref().map(std::path::Path::new),
+        );
+        if let Some(path) = std::env::args().nth(1)
+            && let Err(e) = app.load_scene(&path)
+        {
+            log::error!("Failed to load scene {path}: {e}");
+        }
+        app
+    }
+
+    async fn new_headless(ctx: utils::headless::HeadlessContext) -> Self {
+        let sample_counts = scene_sample_counts(&ctx.adapter, &ctx.device);
+        Self::create(
+            None,
+            None,
+            ctx.device,
+            ctx.queue,
+            ctx.config,
+            sample_counts,
+            None,
+        )
+    }
+
+    fn set_window_size(&mut self, size: PhysicalSize<u32>) {
         if self.size == size {
             return;
         }
@@ -304,133 +1146,563 @@ impl WgpuApp {
         self.change = true;
     }
 
-    fn resize(&mut self) {
-        if self.change {
-            self.change = false;
-            self.config.width = self.size.width;
-            self.config.height = self.size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.camera.aspect = self.config.width as f32 / self.config.height as f32;
+    fn get_size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    fn keyboard_input(&mut self, event: &KeyEvent) -> bool {
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyZ) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.show_depth = !self.show_depth;
+                if self.show_depth {
+                    self.warn_depth_view_unavailable();
+                }
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::Escape)
+            && event.state == ElementState::Pressed
+        {
+            self.set_cursor_grab(false);
+            return true;
+        }
+        if let Some(offset) = light_offset(event.physical_key) {
+            if event.state == ElementState::Pressed
+                && let Some(light) = self.lights.first_mut()
+            {
+                light.position += offset * LIGHT_STEP;
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyG) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.show_grid = !self.show_grid;
+                let instances = if self.show_grid {
+                    instance_grid()
+                } else {
+                    vec![Instance::default()]
+                };
+                self.set_instances(&instances);
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyB) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.use_pbr = !self.use_pbr;
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyN) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.toggle_normal_map();
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyF) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.show_ground = !self.show_ground;
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyL) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.light_setup_index = (self.light_setup_index + 1) % LIGHT_SETUPS;
+                self.lights = light_setup(self.light_setup_index);
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyK) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.show_skybox = !self.show_skybox;
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyT) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                let tonemap = self.hdr.tonemap().next();
+                log::info!("Tonemap: {tonemap:?}");
+                self.hdr.set_tonemap(&self.queue, tonemap);
+            }
+            return true;
+        }
+        if let Some(step) = exposure_step(event.physical_key) {
+            if event.state == ElementState::Pressed {
+                let exposure = self.hdr.exposure() + step;
+                log::info!("Exposure: {exposure:+.1} EV");
+                self.hdr.set_exposure(&self.queue, exposure);
+            }
+            return true;
+        }
+        if let Some(index) = post_effect_index(event.physical_key) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                let enabled = !self.post.is_enabled(index);
+                self.post.set_enabled(index, enabled);
+                if let Some(name) = self.post.name(index) {
+                    log::info!("{name}: {}", if enabled { "on" } else { "off" });
+                }
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyM) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                let index = self
+                    .sample_counts
+                    .iter()
+                    .position(|&count| count == self.msaa.sample_count())
+                    .unwrap_or(0);
+                let sample_count = self.sample_counts[(index + 1) % self.sample_counts.len()];
+                log::info!("MSAA: {sample_count}x");
+                self.set_sample_count(sample_count);
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyP) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.projection_index = (self.projection_index + 1) % PROJECTIONS.len();
+                self.set_projection(PROJECTIONS[self.projection_index]);
+            }
+            return true;
+        }
+        if event.physical_key == PhysicalKey::Code(KeyCode::KeyC) {
+            if event.state == ElementState::Pressed && !event.repeat {
+                self.set_cursor_grab(false);
+                self.active_controller = (self.active_controller + 1) % self.controllers.len();
+            }
+            return true;
+        }
+        self.controller().handle_keyboard_input(event)
+    }
+
+    fn mouse_click(&mut self, state: ElementState, button: MouseButton) -> bool {
+        if self.controller().wants_cursor_grab() {
+            if button == MouseButton::Left && state == ElementState::Pressed && !self.cursor_grabbed
+            {
+                self.set_cursor_grab(true);
+                return true;
+            }
+            return false;
+        }
+        self.controller().handle_mouse_button(state, button)
+    }
+
+    fn mouse_wheel(&mut self, delta: MouseScrollDelta, _phase: TouchPhase) -> bool {
+        self.controller().handle_mouse_wheel(delta);
+        true
+    }
+
+    fn device_input(&mut self, event: &DeviceEvent) -> bool {
+        if let DeviceEvent::MouseMotion { delta } = event
+            && (self.cursor_grabbed || !self.controller().wants_cursor_grab())
+        {
+            self.controller().handle_mouse_motion(delta.0, delta.1);
+            return true;
         }
+        false
+    }
+
+    fn update(&mut self) {
+        self.reload_shaders();
+
+        let now = Instant::now();
+        let dt = now - self.last_update;
+        self.last_update = now;
+
+        let controller = self.controllers[self.active_controller].as_mut();
+        controller.update_camera(&mut self.camera, dt);
+        let mut camera_uniform = *self.camera_uniform.get();
+        camera_uniform.update(&self.camera, self.config.width, self.config.height);
+        self.camera_uniform.update(&self.queue, &camera_uniform);
+        self.lights_uniform.update(
+            &self.queue,
+            &LightsUniform::new(&self.lights)
+                .with_environment(self.env_intensity, self.skybox.max_lod()),
+        );
+        self.shadow_pass
+            .update(&self.queue, &self.lights, &self.camera);
     }
 
-    fn render(&mut self) {
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         if self.size.height == 0 || self.size.width == 0 {
-            return;
+            return Ok(());
         }
 
         self.resize();
 
-        let output = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to get current texture");
+        let Some(surface) = self.surface.as_ref() else {
+            return Ok(());
+        };
+        let output = surface.get_current_texture()?;
 
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        self.render_to_view(&view);
+        output.present();
+
+        Ok(())
+    }
+
+    fn render_to_view(&mut self, view: &wgpu::TextureView) {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
+        // 先从每个光源的视角渲染阴影贴图
+        for layer in 0..self.shadow_pass.layers_in_use() {
+            let mut render_pass = self.shadow_pass.begin_pass(&mut encoder, layer);
+            self.draw_geometry(
+                &mut render_pass,
+                self.shadow_pass.camera_bind_group(layer),
+                false,
+            );
+        }
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    depth_slice: None,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
+                // 多重采样在这里解析到 HDR 纹理，而不是交换链
+                color_attachments: &[Some(self.msaa.color_attachment(
+                    self.hdr.view(),
+                    wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                ))],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.camera.projection.depth_clear_value()),
                         store: wgpu::StoreOp::Store,
-                    },
-                })],
+                    }),
+                    stencil_ops: None,
+                }),
                 ..Default::default()
             });
 
-            render_pass.set_pipeline(&self.pipeline);
+            if self.show_skybox {
+                self.skybox
+                    .draw(&mut render_pass, self.camera_uniform.bind_group());
+            }
 
-            render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+            if self.use_pbr {
+                render_pass.set_pipeline(&self.pbr_pipeline);
+            } else {
+                render_pass.set_pipeline(&self.pipeline);
+            }
+            render_pass.set_bind_group(3, &self.light_bind_group, &[]);
+            self.draw_geometry(
+                &mut render_pass,
+                self.camera_uniform.bind_group(),
+                self.use_pbr,
+            );
 
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            self.light_debug.draw(
+                &mut render_pass,
+                self.camera_uniform.bind_group(),
+                &self.light_bind_group,
+                self.lights.len().min(MAX_LIGHTS) as u32,
+            );
+        }
+
+        self.hdr.process(&mut encoder, self.post.input_view());
+        self.post.render(&self.device, &mut encoder, view);
 
-            render_pass.draw_indexed(0..9, 0, 0..1);
+        if self.show_depth && self.msaa.sample_count() == 1 {
+            self.depth_debug.render(&mut encoder, view);
         }
 
         self.queue.submit(Some(encoder.finish()));
-        output.present();
-    }
-
-    fn update(&mut self) {
-        self.controller.update_camera(&mut self.camera);
-        self.camera_uniform.update_view_proj(&self.camera);
-        self.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
-        );
     }
 }
 
-#[derive(Default)]
-struct WgpuAppHandler {
-    app: Arc<Mutex<Option<WgpuApp>>>,
-}
+#[cfg(test)]
+mod tests {
+    use utils::{
+        headless::{HeadlessApp, TempDir, require_adapter},
+        instance::InstanceRaw,
+        model::{Material, ModelVertex},
+        pbr::PbrMaterial,
+        scene::NodeTransforms,
+        shader_check::ShaderCheck,
+        uniform::UniformBuffer,
+    };
 
-impl ApplicationHandler for WgpuAppHandler {
-    fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        if self.app.lock().is_some() {
-            return;
+    use super::{CAMERA_VISIBILITY, CameraUniform, WgpuApp, light_layout_entries, shaders};
+    use crate::light::{LightUniform, LightsUniform};
+
+    #[test]
+    fn shaders_match_pipeline_layouts() {
+        let camera = UniformBuffer::<CameraUniform>::bind_group_layout_entries(CAMERA_VISIBILITY);
+        let transforms = NodeTransforms::bind_group_layout_entries();
+        let lights = light_layout_entries();
+        let materials = [
+            (
+                "shader.wgsl",
+                include_str!("shader.wgsl"),
+                &Material::bind_group_layout_entries()[..],
+            ),
+            (
+                "pbr.wgsl",
+                include_str!("pbr.wgsl"),
+                &PbrMaterial::bind_group_layout_entries()[..],
+            ),
+        ];
+        for (name, source, material) in materials {
+            let preprocessor = shaders::preprocessor().with_file(name, source);
+            let check = ShaderCheck::new(&preprocessor, name).unwrap();
+            check
+                .vertex_buffers("vs_main", &[ModelVertex::LAYOUT, InstanceRaw::LAYOUT])
+                .unwrap();
+            check
+                .bind_groups(
+                    &["vs_main", "fs_main"],
+                    &[material, &camera, &transforms, &lights],
+                )
+                .unwrap();
         }
+    }
 
-        let window_attributes = WindowAttributes::default().with_title("Beginner-03");
-        let window = event_loop
-            .create_window(window_attributes)
-            .expect("Failed to create window");
+    /// 除了只被引入的文件，`src` 中的每个着色器都可以热重载
+    #[test]
+    fn every_local_shader_is_hot_reloaded() {
+        let Some(mut headless) = require_adapter(
+            pollster::block_on(HeadlessApp::<WgpuApp>::new(winit::dpi::PhysicalSize::new(
+                64, 64,
+            ))),
+            "hot reload test",
+        ) else {
+            return;
+        };
+        let mut reloaded: Vec<String> = headless
+            .app
+            .hot_shaders_mut()
+            .iter()
+            .map(|shader| shader.path().file_name().unwrap().to_string_lossy().into())
+            .collect();
+        reloaded.sort();
 
-        let wgpu_app = pollster::block_on(WgpuApp::new(Arc::new(window)));
+        let included = shaders::preprocessor();
+        let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut expected: Vec<String> = std::fs::read_dir(src)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".wgsl") && !included.has_file(name))
+            .collect();
+        expected.sort();
+        assert_eq!(reloaded, expected);
+    }
 
-        self.app.lock().replace(wgpu_app);
+    #[test]
+    fn uniform_structs_match_wgsl_layout() {
+        let preprocessor = shaders::preprocessor().with_file("pbr.wgsl", include_str!("pbr.wgsl"));
+        let check = ShaderCheck::new(&preprocessor, "pbr.wgsl").unwrap();
+        check
+            .struct_layout::<CameraUniform>(
+                "CameraUniform",
+                utils::struct_fields!(CameraUniform {
+                    view_proj,
+                    view,
+                    proj,
+                    inv_view,
+                    inv_proj,
+                    inv_view_proj,
+                    eye,
+                    viewport,
+                }),
+            )
+            .unwrap();
+        check
+            .struct_layout::<LightUniform>(
+                "Light",
+                utils::struct_fields!(LightUniform {
+                    position,
+                    intensity,
+                    color,
+                    kind,
+                    direction,
+                    cos_outer,
+                    shadow,
+                    cos_inner,
+                }),
+            )
+            .unwrap();
+        check
+            .struct_layout::<LightsUniform>(
+                "Lights",
+                utils::struct_fields!(LightsUniform {
+                    lights,
+                    count,
+                    env_intensity,
+                    env_max_lod,
+                }),
+            )
+            .unwrap();
     }
 
-    fn window_event(
-        &mut self,
-        event_loop: &winit::event_loop::ActiveEventLoop,
-        _window_id: winit::window::WindowId,
-        event: winit::event::WindowEvent,
-    ) {
-        let mut app = self.app.lock();
+    #[test]
+    fn camera_view_matches_golden() {
+        utils::assert_golden!(WgpuApp, "camera");
+    }
 
-        let app = app.as_mut().expect("App not initialized");
+    #[test]
+    fn orthographic_matches_golden() {
+        utils::assert_golden!(WgpuApp, "orthographic", |app: &mut WgpuApp| {
+            app.set_projection(super::PROJECTIONS[1]);
+        });
+    }
 
-        match event {
-            winit::event::WindowEvent::Resized(physical_size) => {
-                app.set_size(physical_size);
-            }
-            winit::event::WindowEvent::RedrawRequested => {
-                app.update();
+    #[test]
+    fn reverse_z_matches_perspective() {
+        utils::assert_golden!(WgpuApp, "camera", |app: &mut WgpuApp| {
+            app.set_projection(super::PROJECTIONS[2]);
+        });
+    }
 
-                app.window.pre_present_notify();
+    /// 一个红色三角形，放在父节点平移、子节点缩放的层级中
+    const TRIANGLE_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "translation": [0.3, -0.5, 0.2], "children": [1] },
+            { "scale": [0.5, 0.5, 0.5], "mesh": 0 }
+        ],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1] } }],
+        "buffers": [{ "uri": "triangle.bin", "byteLength": 36 }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0, 0, 0], "max": [1, 1, 0]
+        }]
+    }"#;
 
-                app.render();
+    #[test]
+    fn gltf_scene_matches_golden() {
+        let dir = TempDir::new("beginner-03-scene");
+        std::fs::write(dir.join("triangle.gltf"), TRIANGLE_GLTF).unwrap();
+        let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        std::fs::write(dir.join("triangle.bin"), bytemuck::cast_slice(&positions)).unwrap();
 
-                app.window.request_redraw();
-            }
-            winit::event::WindowEvent::KeyboardInput { event, .. } => {
-                app.controller.handle_keyboard_input(event);
-            }
-            winit::event::WindowEvent::CloseRequested => event_loop.exit(),
-            _ => {}
-        }
+        utils::assert_golden!(WgpuApp, "gltf_scene", |app: &mut WgpuApp| {
+            app.load_scene(dir.join("triangle.gltf")).unwrap();
+        });
+    }
+
+    #[test]
+    fn instances_match_golden() {
+        utils::assert_golden!(WgpuApp, "instances", |app: &mut WgpuApp| {
+            let instances: Vec<_> = utils::instance::grid(5, 5, 0.4)
+                .into_iter()
+                .enumerate()
+                .map(|(i, instance)| utils::instance::Instance {
+                    rotation: glam::Quat::from_rotation_z(i as f32 * 0.3),
+                    scale: glam::Vec3::splat(0.3),
+                    tint: [1.0 - i as f32 / 25.0, 1.0, i as f32 / 25.0, 1.0],
+                    ..instance
+                })
+                .collect();
+            app.set_instances(&instances);
+        });
+    }
+
+    #[test]
+    fn moved_light_matches_golden() {
+        utils::assert_golden!(WgpuApp, "light", |app: &mut WgpuApp| {
+            app.lights[0].position = glam::Vec3::new(-0.3, 0.2, 0.4);
+            app.lights[0].color = glam::Vec3::new(1.0, 0.8, 0.6);
+        });
+    }
+
+    #[test]
+    fn shadows_match_golden() {
+        utils::assert_golden!(WgpuApp, "shadows", |app: &mut WgpuApp| {
+            app.show_ground = true;
+            app.lights = super::light_setup(2);
+        });
+    }
+
+    #[test]
+    fn pbr_matches_golden() {
+        utils::assert_golden!(WgpuApp, "pbr", |app: &mut WgpuApp| {
+            app.use_pbr = true;
+            app.toggle_normal_map();
+            app.lights.push(super::Light {
+                position: glam::Vec3::new(-1.0, 0.5, 1.0),
+                color: glam::Vec3::new(0.4, 0.6, 1.0),
+                intensity: 3.0,
+                kind: super::LightKind::Point,
+            });
+        });
+    }
+
+    #[test]
+    fn normal_map_matches_golden() {
+        utils::assert_golden!(WgpuApp, "normal_map", |app: &mut WgpuApp| {
+            app.use_pbr = true;
+        });
+    }
+
+    #[test]
+    fn agx_tonemap_matches_golden() {
+        utils::assert_golden!(WgpuApp, "agx", |app: &mut WgpuApp| {
+            app.use_pbr = true;
+            app.hdr.set_tonemap(&app.queue, utils::hdr::Tonemap::Agx);
+            app.hdr.set_exposure(&app.queue, 1.0);
+        });
+    }
+
+    #[test]
+    fn post_chain_matches_golden() {
+        utils::assert_golden!(WgpuApp, "post", |app: &mut WgpuApp| {
+            app.show_ground = true;
+            app.post.set_all_enabled(true);
+        });
+    }
+
+    #[test]
+    fn msaa_matches_golden() {
+        utils::assert_golden!(WgpuApp, "msaa", |app: &mut WgpuApp| {
+            app.show_ground = true;
+            app.set_sample_count(4);
+            assert_eq!(app.msaa.sample_count(), 4);
+        });
+    }
+
+    #[test]
+    fn skybox_matches_golden() {
+        utils::assert_golden!(WgpuApp, "skybox", |app: &mut WgpuApp| {
+            app.use_pbr = true;
+            app.show_ground = true;
+            app.lights = super::light_setup(1);
+            // 光滑的金属五边形反射天空
+            app.pentagon_pbr.factors.metallic = 1.0;
+            app.pentagon_pbr.factors.roughness = 0.2;
+            app.pentagon_pbr.update(&app.queue);
+            app.camera.target = glam::Vec3::new(0.0, 0.6, 0.0);
+        });
+    }
+
+    #[test]
+    fn depth_debug_matches_golden() {
+        utils::assert_golden!(WgpuApp, "depth", |app: &mut WgpuApp| {
+            app.show_depth = true;
+        });
+    }
+
+    #[test]
+    fn depth_debug_follows_projection() {
+        utils::assert_golden!(WgpuApp, "depth_orthographic", |app: &mut WgpuApp| {
+            app.show_depth = true;
+            app.set_projection(super::PROJECTIONS[1]);
+        });
+        utils::assert_golden!(WgpuApp, "depth_reverse_z", |app: &mut WgpuApp| {
+            app.show_depth = true;
+            app.set_projection(super::PROJECTIONS[2]);
+        });
     }
 }
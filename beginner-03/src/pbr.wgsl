@@ -0,0 +1,194 @@
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) tex_coord: vec2f,
+    @location(2) normal: vec3f,
+    @location(3) tangent: vec4f,
+}
+
+struct InstanceInput {
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+    @location(9) tint: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) tex_coord: vec2f,
+    @location(1) tint: vec4f,
+    @location(2) world_position: vec3f,
+    @location(3) world_normal: vec3f,
+    @location(4) world_tangent: vec4f,
+};
+
+#include "camera.wgsl"
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(2) @binding(0)
+var<uniform> model: mat4x4f;
+
+#define ENVIRONMENT
+#include "lighting.wgsl"
+
+@vertex
+fn vs_main(
+    input: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let instance_model = mat4x4f(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+    let world_model = model * instance_model;
+    let world_position = world_model * vec4f(input.position, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = camera.proj * camera.view * world_position;
+    out.tex_coord = input.tex_coord;
+    out.tint = instance.tint;
+    out.world_position = world_position.xyz;
+    // 假设缩放是均匀的，否则需要使用逆转置矩阵变换法线
+    out.world_normal = (world_model * vec4f(input.normal, 0.0)).xyz;
+    out.world_tangent = vec4f((world_model * vec4f(input.tangent.xyz, 0.0)).xyz, input.tangent.w);
+    return out;
+}
+
+struct Material {
+    base_color: vec4f,
+    emissive: vec3f,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+}
+@group(0) @binding(0)
+var<uniform> material: Material;
+@group(0) @binding(1)
+var t_base_color: texture_2d<f32>;
+@group(0) @binding(2)
+var t_metallic_roughness: texture_2d<f32>;
+@group(0) @binding(3)
+var t_normal: texture_2d<f32>;
+@group(0) @binding(4)
+var t_occlusion: texture_2d<f32>;
+@group(0) @binding(5)
+var t_emissive: texture_2d<f32>;
+@group(0) @binding(6)
+var s_material: sampler;
+
+const PI: f32 = 3.14159265359;
+
+// GGX / Trowbridge-Reitz 法线分布
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith-Schlick 几何遮蔽，直接光照使用 k = (r + 1)^2 / 8
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3f) -> vec3f {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// 环境光照的菲涅尔项，粗糙表面在掠射角的反射较弱
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3f, roughness: f32) -> vec3f {
+    return f0 + (max(vec3f(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// 预积分的环境 BRDF 的解析近似 (Karis)，代替查找表
+fn env_brdf_approx(f0: vec3f, roughness: f32, n_dot_v: f32) -> vec3f {
+    let c0 = vec4f(-1.0, -0.0275, -0.572, 0.022);
+    let c1 = vec4f(1.0, 0.0425, 1.04, -0.04);
+    let r = roughness * c0 + c1;
+    let a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+    let ab = vec2f(-1.04, 1.04) * a004 + r.zw;
+    return f0 * ab.x + ab.y;
+}
+
+// 用环境贴图的 mip 链近似预滤波：漫反射取最粗糙的级别，镜面反射按粗糙度选择级别
+fn ambient_light(normal: vec3f, view_dir: vec3f, n_dot_v: f32, base_color: vec3f, f0: vec3f, metallic: f32, roughness: f32) -> vec3f {
+    let irradiance = textureSampleLevel(t_environment, s_environment, normal, lights.env_max_lod).rgb;
+    let reflected = reflect(-view_dir, normal);
+    let prefiltered = textureSampleLevel(t_environment, s_environment, reflected, roughness * lights.env_max_lod).rgb;
+
+    let f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    let k_d = (vec3f(1.0) - f) * (1.0 - metallic);
+    let diffuse = k_d * irradiance * base_color;
+    let specular = prefiltered * env_brdf_approx(f0, roughness, n_dot_v);
+    return (diffuse + specular) * lights.env_intensity;
+}
+
+// 把切线空间的法线变换到世界空间，副切线的方向由 tangent.w 决定 (MikkTSpace)
+fn perturb_normal(normal: vec3f, tangent: vec4f, tangent_normal: vec3f) -> vec3f {
+    // 插值后切线不再与法线正交，先做一次 Gram-Schmidt 正交化
+    let t = normalize(tangent.xyz - normal * dot(normal, tangent.xyz));
+    let b = cross(normal, t) * tangent.w;
+    return normalize(mat3x3f(t, b, normal) * tangent_normal);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let base_color = textureSample(t_base_color, s_material, in.tex_coord) * material.base_color * in.tint;
+    let metallic_roughness = textureSample(t_metallic_roughness, s_material, in.tex_coord);
+    let occlusion_sample = textureSample(t_occlusion, s_material, in.tex_coord).r;
+    let emissive = textureSample(t_emissive, s_material, in.tex_coord).rgb * material.emissive;
+    var tangent_normal = textureSample(t_normal, s_material, in.tex_coord).xyz * 2.0 - 1.0;
+    tangent_normal = vec3f(tangent_normal.xy * material.normal_scale, tangent_normal.z);
+
+    let roughness = clamp(metallic_roughness.g * material.roughness, 0.04, 1.0);
+    let metallic = clamp(metallic_roughness.b * material.metallic, 0.0, 1.0);
+    let occlusion = mix(1.0, occlusion_sample, material.occlusion_strength);
+
+    let normal = perturb_normal(normalize(in.world_normal), in.world_tangent, tangent_normal);
+    let view_dir = normalize(camera.eye - in.world_position);
+    let n_dot_v = max(dot(normal, view_dir), 1e-4);
+
+    // 非金属的基础反射率约为 0.04
+    let f0 = mix(vec3f(0.04), base_color.rgb, metallic);
+
+    var radiance_out = vec3f(0.0);
+    for (var i = 0u; i < lights.count; i++) {
+        let light = lights.lights[i];
+        let sample = sample_light(light, in.world_position);
+        let light_dir = sample.direction;
+        let half_dir = normalize(light_dir + view_dir);
+
+        let n_dot_l = max(dot(normal, light_dir), 0.0);
+        let n_dot_h = max(dot(normal, half_dir), 0.0);
+        let h_dot_v = max(dot(half_dir, view_dir), 0.0);
+
+        let radiance = sample.radiance * shadow_factor(light, in.world_position, normal);
+
+        let d = distribution_ggx(n_dot_h, roughness);
+        let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+        let f = fresnel_schlick(h_dot_v, f0);
+
+        let specular = d * g * f / (4.0 * n_dot_v * max(n_dot_l, 1e-4));
+        // 金属没有漫反射
+        let k_d = (vec3f(1.0) - f) * (1.0 - metallic);
+        radiance_out += (k_d * base_color.rgb / PI + specular) * radiance * n_dot_l;
+    }
+
+    let ambient = ambient_light(normal, view_dir, n_dot_v, base_color.rgb, f0, metallic, roughness) * occlusion;
+    // 输出线性的 HDR 颜色，由后续的全屏通道做色调映射
+    let color = ambient + radiance_out + emissive;
+
+    if base_color.a < material.alpha_cutoff {
+        discard;
+    }
+    return vec4f(color, base_color.a);
+}
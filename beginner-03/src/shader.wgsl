@@ -1,26 +1,56 @@
 struct VertexInput {
     @location(0) position: vec3f,
     @location(1) tex_coord: vec2f,
+    @location(2) normal: vec3f,
+}
+
+struct InstanceInput {
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+    @location(9) tint: vec4f,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4f,
     @location(0) tex_coord: vec2f,
+    @location(1) tint: vec4f,
+    @location(2) world_position: vec3f,
+    @location(3) world_normal: vec3f,
 };
 
-struct CameraUniform {
-    view_proj: mat4x4f,
-}
+#include "camera.wgsl"
 @group(1) @binding(0)
 var<uniform> camera: CameraUniform;
 
+@group(2) @binding(0)
+var<uniform> model: mat4x4f;
+
+#include "lighting.wgsl"
+
 @vertex
 fn vs_main(
-    input: VertexInput
+    input: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
+    let instance_model = mat4x4f(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+
+    let world_model = model * instance_model;
+    let world_position = world_model * vec4f(input.position, 1.0);
+
     var out: VertexOutput;
-    out.clip_position = camera.view_proj * vec4f(input.position, 1.0);
+    out.clip_position = camera.proj * camera.view * world_position;
     out.tex_coord = input.tex_coord;
+    out.world_position = world_position.xyz;
+    // 假设缩放是均匀的，否则需要使用逆转置矩阵变换法线
+    out.world_normal = (world_model * vec4f(input.normal, 0.0)).xyz;
+    out.tint = instance.tint;
     return out;
 }
 
@@ -29,7 +59,30 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+const AMBIENT_STRENGTH: f32 = 0.1;
+const SHININESS: f32 = 32.0;
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coord);
+    let base_color = textureSample(t_diffuse, s_diffuse, in.tex_coord) * in.tint;
+
+    let normal = normalize(in.world_normal);
+    let view_dir = normalize(camera.eye - in.world_position);
+
+    var ambient = vec3f(0.0);
+    var diffuse = vec3f(0.0);
+    var specular = vec3f(0.0);
+    for (var i = 0u; i < lights.count; i++) {
+        let light = lights.lights[i];
+        let sample = sample_light(light, in.world_position);
+        let light_dir = sample.direction;
+        let half_dir = normalize(light_dir + view_dir);
+
+        let radiance = sample.radiance * shadow_factor(light, in.world_position, normal);
+        ambient += light.color * AMBIENT_STRENGTH;
+        diffuse += max(dot(normal, light_dir), 0.0) * radiance;
+        specular += pow(max(dot(normal, half_dir), 0.0), SHININESS) * radiance;
+    }
+
+    return vec4f(base_color.rgb * (ambient + diffuse) + specular, base_color.a);
 }
\ No newline at end of file
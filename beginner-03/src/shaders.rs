@@ -0,0 +1,11 @@
+//! 多个着色器共用的 WGSL 文件
+
+use utils::preprocess::Preprocessor;
+
+/// 注册了所有可以被 `#include` 的文件的预处理器
+pub fn preprocessor() -> Preprocessor {
+    Preprocessor::new()
+        .with_file("camera.wgsl", include_str!("camera.wgsl"))
+        .with_file("lights.wgsl", include_str!("lights.wgsl"))
+        .with_file("lighting.wgsl", include_str!("lighting.wgsl"))
+}
@@ -0,0 +1,390 @@
+use bytemuck::{Pod, Zeroable};
+use utils::{
+    hot_reload::{HotShader, capture_device_errors},
+    instance::InstanceRaw,
+    model::ModelVertex,
+    shadow::{self, ShadowMap},
+    uniform::UniformBuffer,
+};
+
+use crate::{
+    camera::{Camera, CameraUniform},
+    light::{Light, LightKind, assign_shadow_layers},
+    shaders,
+};
+
+/// 方向光的级联数量，不能超过 `cascade_splits` 的 4 个分量
+pub const CASCADE_COUNT: usize = 3;
+/// 阴影贴图数组的层数，即所有光源可以使用的阴影视角总数
+pub const MAX_SHADOW_LAYERS: usize = 8;
+/// 阴影贴图的边长
+const SHADOW_SIZE: u32 = 1024;
+/// 超过该距离的物体不接收方向光的阴影
+const SHADOW_DISTANCE: f32 = 20.0;
+/// 对数分割与均匀分割的混合比例
+const CASCADE_LAMBDA: f32 = 0.75;
+/// 级联的近平面向光源方向延伸的距离，使相机视锥体之外的物体也能投射阴影
+const CASTER_DISTANCE: f32 = 10.0;
+/// 聚光灯阴影的近平面和远平面
+const SPOT_NEAR: f32 = 0.05;
+const SPOT_FAR: f32 = 50.0;
+/// 阴影只在片元着色器中采样
+const SHADOW_VISIBILITY: wgpu::ShaderStages = wgpu::ShaderStages::FRAGMENT;
+
+/// 与着色器中的 `Shadows` 对应
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct ShadowUniform {
+    /// 每一层阴影贴图的视图投影矩阵
+    pub view_proj: [[[f32; 4]; 4]; MAX_SHADOW_LAYERS],
+    /// 每一级级联远端在相机视图空间中的深度
+    pub cascade_splits: [f32; 4],
+}
+
+impl ShadowUniform {
+    pub fn new(lights: &[Light], camera: &Camera) -> Self {
+        let view = camera.build_view_matrix();
+        let proj = camera.build_projection_matrix();
+        let (near, far) = camera.projection.clip_planes();
+        let splits = shadow::cascade_splits(
+            near,
+            far.min(SHADOW_DISTANCE),
+            CASCADE_COUNT,
+            CASCADE_LAMBDA,
+        );
+
+        let mut uniform = Self::zeroed();
+        uniform.cascade_splits[..CASCADE_COUNT].copy_from_slice(&splits);
+
+        for (light, layer) in lights.iter().zip(assign_shadow_layers(lights)) {
+            let Some(layer) = layer else {
+                continue;
+            };
+            let layer = layer as usize;
+            match light.kind {
+                LightKind::Point => {}
+                LightKind::Directional { direction } => {
+                    let mut cascade_near = near;
+                    for (cascade, &cascade_far) in splits.iter().enumerate() {
+                        let corners =
+                            shadow::frustum_slice_corners(view, proj, cascade_near, cascade_far);
+                        let matrix = shadow::directional_light_matrix(
+                            direction,
+                            &corners,
+                            SHADOW_SIZE,
+                            CASTER_DISTANCE,
+                        );
+                        uniform.view_proj[layer + cascade] = matrix.to_cols_array_2d();
+                        cascade_near = cascade_far;
+                    }
+                }
+                LightKind::Spot { direction, angle } => {
+                    let matrix = shadow::spot_light_matrix(
+                        light.position,
+                        direction,
+                        angle,
+                        SPOT_NEAR,
+                        SPOT_FAR,
+                    );
+                    uniform.view_proj[layer] = matrix.to_cols_array_2d();
+                }
+            }
+        }
+        uniform
+    }
+
+    /// 被光源使用的层数，未使用的层不需要渲染
+    fn layers_in_use(lights: &[Light]) -> usize {
+        lights
+            .iter()
+            .zip(assign_shadow_layers(lights))
+            .filter_map(|(light, layer)| {
+                layer.map(|layer| (layer + light.shadow_layers()) as usize)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// 从光源视角渲染场景深度的阴影通道
+///
+/// 管线布局与主管线相同：材质位于 `@group(0)`（不使用），光源视角作为相机位于 `@group(1)`，
+/// 节点变换位于 `@group(2)`，因此可以用同样的绘制代码渲染阴影
+pub struct ShadowPass {
+    map: ShadowMap,
+    shader: HotShader,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    /// 只使用其中的缓冲，绑定到主管线的光源绑定组
+    uniform: UniformBuffer<ShadowUniform>,
+    /// 每一层一个只填写了 `view_proj` 的相机，绑定组使用主相机的布局
+    layer_cameras: Vec<UniformBuffer<CameraUniform>>,
+    layer_bind_groups: Vec<wgpu::BindGroup>,
+    layers_in_use: usize,
+}
+
+impl ShadowPass {
+    pub fn new(
+        device: &wgpu::Device,
+        material_layout: &wgpu::BindGroupLayout,
+        camera_layout: &wgpu::BindGroupLayout,
+        transform_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let map = ShadowMap::new(device, SHADOW_SIZE, MAX_SHADOW_LAYERS as u32);
+
+        let uniform = UniformBuffer::new(
+            device,
+            "Shadow Uniform",
+            SHADOW_VISIBILITY,
+            ShadowUniform::zeroed(),
+        );
+
+        let (layer_cameras, layer_bind_groups) = (0..MAX_SHADOW_LAYERS)
+            .map(|_| {
+                let camera = UniformBuffer::new(
+                    device,
+                    "Shadow Camera",
+                    wgpu::ShaderStages::VERTEX,
+                    CameraUniform::new(),
+                );
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Shadow Camera Bind Group"),
+                    layout: camera_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: camera.buffer().as_entire_binding(),
+                    }],
+                });
+                (camera, bind_group)
+            })
+            .unzip();
+
+        let shader = utils::hot_shader!(
+            device,
+            "Shadow Shader",
+            "shadow.wgsl",
+            &shaders::preprocessor()
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[material_layout, camera_layout, transform_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, shader.module());
+
+        Self {
+            map,
+            shader,
+            pipeline_layout,
+            pipeline,
+            uniform,
+            layer_cameras,
+            layer_bind_groups,
+            layers_in_use: 0,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[ModelVertex::LAYOUT, InstanceRaw::LAYOUT],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // 五边形等单面的网格从背面也要投射阴影
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: ShadowMap::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                // 按斜率偏移深度，减少接收面上的阴影痤疮
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            fragment: None,
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    pub fn shader(&self) -> &HotShader {
+        &self.shader
+    }
+
+    pub fn shader_mut(&mut self) -> &mut HotShader {
+        &mut self.shader
+    }
+
+    /// 着色器重新加载后重新创建管线；创建失败时保留原来的管线
+    pub fn recreate_pipeline(&mut self, device: &wgpu::Device) {
+        match capture_device_errors(device, || {
+            Self::create_pipeline(device, &self.pipeline_layout, self.shader.module())
+        }) {
+            Ok(pipeline) => self.pipeline = pipeline,
+            Err(e) => log::error!(
+                "Failed to create pipeline for {}: {e}",
+                self.shader.path().display()
+            ),
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, lights: &[Light], camera: &Camera) {
+        let uniform = ShadowUniform::new(lights, camera);
+        self.uniform.update(queue, &uniform);
+
+        self.layers_in_use = ShadowUniform::layers_in_use(lights);
+        for (layer_camera, view_proj) in self
+            .layer_cameras
+            .iter_mut()
+            .zip(uniform.view_proj)
+            .take(self.layers_in_use)
+        {
+            let mut camera = CameraUniform::new();
+            camera.view_proj = view_proj;
+            layer_camera.update(queue, &camera);
+        }
+    }
+
+    /// 光源绑定组中的阴影资源：`@binding(1)` 为 [`ShadowUniform`]，`@binding(2)` 为阴影贴图，
+    /// `@binding(3)` 为比较采样器
+    pub fn bind_group_entries(&self) -> [wgpu::BindGroupEntry<'_>; 3] {
+        [
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: self.uniform.buffer().as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&self.map.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&self.map.sampler),
+            },
+        ]
+    }
+
+    /// 与 [`ShadowPass::bind_group_entries`] 对应的布局项
+    pub fn bind_group_layout_entries() -> [wgpu::BindGroupLayoutEntry; 3] {
+        let [uniform] =
+            UniformBuffer::<ShadowUniform>::bind_group_layout_entries(SHADOW_VISIBILITY);
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                ..uniform
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: SHADOW_VISIBILITY,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    sample_type: wgpu::TextureSampleType::Depth,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: SHADOW_VISIBILITY,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            },
+        ]
+    }
+
+    /// 需要渲染的阴影贴图层数
+    pub fn layers_in_use(&self) -> usize {
+        self.layers_in_use
+    }
+
+    /// 渲染第 `layer` 层时代替相机绑定到 `@group(1)` 的光源视角
+    pub fn camera_bind_group(&self, layer: usize) -> &wgpu::BindGroup {
+        &self.layer_bind_groups[layer]
+    }
+
+    /// 开启渲染第 `layer` 层的只有深度的渲染通道，并设置好管线
+    pub fn begin_pass<'e>(
+        &self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        layer: usize,
+    ) -> wgpu::RenderPass<'e> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.map.layer_views[layer],
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Mat4, Vec3};
+
+    use super::*;
+    use crate::camera::Projection;
+
+    #[test]
+    fn uniform_layout_matches_wgsl() {
+        assert_eq!(std::mem::offset_of!(ShadowUniform, cascade_splits), 512);
+        assert_eq!(std::mem::size_of::<ShadowUniform>(), 528);
+    }
+
+    #[test]
+    fn directional_light_fills_one_layer_per_cascade() {
+        let camera = Camera {
+            eye: Vec3::new(0.0, 1.0, 2.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect: 1.0,
+            projection: Projection::InfiniteReverseZ {
+                fovy: 45.0,
+                znear: 0.1,
+            },
+        };
+        let sun = Light {
+            position: Vec3::ZERO,
+            color: Vec3::ONE,
+            intensity: 1.0,
+            kind: LightKind::Directional {
+                direction: Vec3::new(0.3, -1.0, -0.5),
+            },
+        };
+        let uniform = ShadowUniform::new(&[sun], &camera);
+
+        assert_eq!(ShadowUniform::layers_in_use(&[sun]), CASCADE_COUNT);
+        assert!((uniform.cascade_splits[CASCADE_COUNT - 1] - SHADOW_DISTANCE).abs() < 1e-3);
+        // 相机前方 1 个单位处位于第一级级联之内
+        let point = camera.eye + (camera.target - camera.eye).normalize();
+        let ndc = Mat4::from_cols_array_2d(&uniform.view_proj[0]).project_point3(point);
+        assert!(ndc.x.abs() < 1.0 && ndc.y.abs() < 1.0 && (0.0..1.0).contains(&ndc.z));
+        assert_eq!(uniform.view_proj[CASCADE_COUNT], [[0.0; 4]; 4]);
+    }
+}
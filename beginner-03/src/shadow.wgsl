@@ -0,0 +1,29 @@
+struct InstanceInput {
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+}
+
+#include "camera.wgsl"
+// 光源的视图投影矩阵，只有 view_proj 有效
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(2) @binding(0)
+var<uniform> model: mat4x4f;
+
+// 只写深度，不需要片元着色器
+@vertex
+fn vs_main(
+    @location(0) position: vec3f,
+    instance: InstanceInput,
+) -> @builtin(position) vec4f {
+    let instance_model = mat4x4f(
+        instance.model_0,
+        instance.model_1,
+        instance.model_2,
+        instance.model_3,
+    );
+    return camera.view_proj * model * instance_model * vec4f(position, 1.0);
+}
@@ -0,0 +1,241 @@
+use glam::Vec3;
+use utils::{
+    cubemap::CubeTexture,
+    hot_reload::{HotShader, capture_device_errors},
+    texture::Texture,
+};
+
+use crate::shaders;
+
+/// 立方体贴图的边长
+pub const SKY_SIZE: u32 = 64;
+
+/// 在场景后面绘制环境立方体贴图，同一张贴图也用于 PBR 的环境光照
+///
+/// 全屏三角形不写入深度，并且总是通过深度测试，需要在其他物体之前绘制
+pub struct Skybox {
+    environment: CubeTexture,
+    shader: HotShader,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Skybox {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        camera_layout: &wgpu::BindGroupLayout,
+        environment: CubeTexture,
+        multisample: wgpu::MultisampleState,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Skybox Bind Group Layout"),
+            entries: &Self::environment_layout_entries(0),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox Bind Group"),
+            layout: &bind_group_layout,
+            entries: &environment_entries(&environment, 0),
+        });
+
+        let shader = utils::hot_shader!(
+            device,
+            "Skybox Shader",
+            "skybox.wgsl",
+            &shaders::preprocessor()
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = Self::create_pipeline(
+            device,
+            &pipeline_layout,
+            shader.module(),
+            format,
+            multisample,
+        );
+
+        Self {
+            environment,
+            shader,
+            pipeline_layout,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        multisample: wgpu::MultisampleState,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skybox Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            // 深度附件与场景共用，但天空盒不参与深度测试，与投影方式无关
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample,
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    pub fn shader(&self) -> &HotShader {
+        &self.shader
+    }
+
+    pub fn shader_mut(&mut self) -> &mut HotShader {
+        &mut self.shader
+    }
+
+    /// 切换采样数或着色器重新加载后需要重新创建管线；创建失败时保留原来的管线
+    pub fn recreate_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        multisample: wgpu::MultisampleState,
+    ) {
+        match capture_device_errors(device, || {
+            Self::create_pipeline(
+                device,
+                &self.pipeline_layout,
+                self.shader.module(),
+                format,
+                multisample,
+            )
+        }) {
+            Ok(pipeline) => self.pipeline = pipeline,
+            Err(e) => log::error!(
+                "Failed to create pipeline for {}: {e}",
+                self.shader.path().display()
+            ),
+        }
+    }
+
+    /// 最粗糙的表面采样的 mip 级别
+    pub fn max_lod(&self) -> f32 {
+        (self.environment.mip_level_count() - 1) as f32
+    }
+
+    /// 光源绑定组中的环境贴图：`@binding(first)` 为立方体贴图，`@binding(first + 1)` 为采样器
+    pub fn bind_group_entries(&self, first: u32) -> [wgpu::BindGroupEntry<'_>; 2] {
+        environment_entries(&self.environment, first)
+    }
+
+    /// 与 [`Skybox::bind_group_entries`] 对应的布局项
+    pub fn environment_layout_entries(first: u32) -> [wgpu::BindGroupLayoutEntry; 2] {
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: first,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: first + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn environment_entries(environment: &CubeTexture, first: u32) -> [wgpu::BindGroupEntry<'_>; 2] {
+    [
+        wgpu::BindGroupEntry {
+            binding: first,
+            resource: wgpu::BindingResource::TextureView(&environment.view),
+        },
+        wgpu::BindGroupEntry {
+            binding: first + 1,
+            resource: wgpu::BindingResource::Sampler(&environment.sampler),
+        },
+    ]
+}
+
+/// 程序生成的天空：地平线到天顶的渐变和太阳，地平线以下是较暗的地面
+///
+/// `sun` 为指向太阳的方向，返回线性 HDR 颜色
+pub fn procedural_sky(dir: Vec3, sun: Vec3) -> Vec3 {
+    const ZENITH: Vec3 = Vec3::new(0.15, 0.3, 0.65);
+    const HORIZON: Vec3 = Vec3::new(0.6, 0.7, 0.85);
+    const GROUND: Vec3 = Vec3::new(0.12, 0.11, 0.1);
+    const SUN_COLOR: Vec3 = Vec3::new(1.0, 0.95, 0.85);
+
+    let dir = dir.normalize();
+    if dir.y < 0.0 {
+        // 地平线附近与天空平滑过渡
+        return GROUND.lerp(HORIZON * 0.5, (1.0 + dir.y * 10.0).max(0.0));
+    }
+
+    let sky = HORIZON.lerp(ZENITH, dir.y.powf(0.5));
+    let cos_sun = dir.dot(sun.normalize()).max(0.0);
+    let disc = cos_sun.powf(2000.0) * 50.0;
+    let glow = cos_sun.powf(16.0) * 0.5;
+    sky + SUN_COLOR * (disc + glow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_is_brighter_than_sky_and_ground_is_darkest() {
+        let sun = Vec3::new(0.4, 1.0, 0.6);
+        let luminance = |dir| procedural_sky(dir, sun).dot(Vec3::new(0.2126, 0.7152, 0.0722));
+
+        assert!(luminance(sun) > 1.0);
+        assert!(luminance(sun) > luminance(Vec3::Y) * 10.0);
+        assert!(luminance(Vec3::NEG_Y) < luminance(Vec3::Z));
+    }
+}
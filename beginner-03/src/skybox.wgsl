@@ -0,0 +1,40 @@
+#include "camera.wgsl"
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var t_environment: texture_cube<f32>;
+@group(1) @binding(1)
+var s_environment: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) ndc: vec2f,
+};
+
+// 覆盖整个屏幕的三角形
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2f(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    out.ndc = uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0);
+    out.clip_position = vec4f(out.ndc, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    // 用逆视图投影矩阵把同一像素上两个深度的点还原到世界空间，两点之差就是视线方向；
+    // 不使用近平面和远平面，无限远投影的远平面无法还原
+    let a = camera.inv_view_proj * vec4f(in.ndc, 0.25, 1.0);
+    let b = camera.inv_view_proj * vec4f(in.ndc, 0.75, 1.0);
+    var dir = normalize(b.xyz / b.w - a.xyz / a.w);
+    // 反向 Z 时深度大的点更近，保证方向朝向相机前方
+    let forward = -camera.inv_view[2].xyz;
+    if dot(dir, forward) < 0.0 {
+        dir = -dir;
+    }
+    return vec4f(textureSampleLevel(t_environment, s_environment, dir, 0.0).rgb, 1.0);
+}
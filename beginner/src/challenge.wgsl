@@ -1,12 +1,5 @@
-struct VertexInput {
-    @location(0) clip_position: vec3f,
-    @location(1) color: vec4f,
-};
-
-struct VertexOutput {
-    @builtin(position) clip_position: vec4f,
-    @location(0) color: vec4f,
-};
+#define VERTEX_COLOR
+#include "common.wgsl"
 
 @vertex
 fn vs_main(
@@ -14,7 +7,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.color = model.color;
-    out.clip_position = vec4f(model.clip_position, 1.0);
+    out.clip_position = vec4f(model.position, 1.0);
     return out;
 }
 
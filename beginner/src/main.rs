@@ -1,24 +1,34 @@
 use std::sync::Arc;
 
 use bytemuck::{Pod, Zeroable};
-use parking_lot::Mutex;
+use utils::{
+    framework::{WgpuAppAction, run},
+    preprocess::Preprocessor,
+    vertex::VertexLayout,
+};
 use wgpu::util::DeviceExt;
-use winit::{application::ApplicationHandler, event, keyboard::PhysicalKey, window::Window};
+use winit::{event, keyboard::PhysicalKey, window::Window};
 
-fn main() {
-    utils::init_logger();
-    let event_loop = winit::event_loop::EventLoop::new().unwrap();
-    let mut app = WgpuAppHandler::default();
-    event_loop.run_app(&mut app).unwrap();
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run::<WgpuApp>("WGPU App")?;
+    Ok(())
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     color: [f32; 4],
 }
 
+/// 注册了两个着色器和共用的 `common.wgsl` 的预处理器
+fn preprocessor() -> Preprocessor {
+    Preprocessor::new()
+        .with_common()
+        .with_file("shader.wgsl", include_str!("shader.wgsl"))
+        .with_file("challenge.wgsl", include_str!("challenge.wgsl"))
+}
+
 const VERTICES: &[Vertex] = &[
     Vertex {
         position: [-0.0868241, 0.49240386, 0.0],
@@ -45,9 +55,8 @@ const VERTICES: &[Vertex] = &[
 const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
 
 struct WgpuApp {
-    #[allow(unused)]
-    window: Arc<Window>,
-    surface: wgpu::Surface<'static>,
+    /// 无头模式下没有窗口表面
+    surface: Option<wgpu::Surface<'static>>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
@@ -62,54 +71,13 @@ struct WgpuApp {
 }
 
 impl WgpuApp {
-    async fn new(window: Arc<Window>) -> Self {
-        utils::init(window.clone());
-
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-        let surface = instance.create_surface(window.clone()).unwrap();
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                required_limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                },
-                memory_hints: wgpu::MemoryHints::Performance,
-                trace: wgpu::Trace::Off,
-            })
-            .await
-            .unwrap();
-
-        let mut size = window.inner_size();
-        size.width = size.width.max(1);
-        size.height = size.height.max(1);
-        let caps = surface.get_capabilities(&adapter);
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: caps.formats[0],
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            desired_maximum_frame_latency: 2,
-            alpha_mode: caps.alpha_modes[0],
-            view_formats: vec![],
-        };
-        surface.configure(&device, &config);
+    fn create(
+        surface: Option<wgpu::Surface<'static>>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let size = winit::dpi::PhysicalSize::new(config.width, config.height);
 
         let clear_color = wgpu::Color {
             r: 0.1,
@@ -118,10 +86,8 @@ impl WgpuApp {
             a: 1.0,
         };
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
+        let preprocessor = preprocessor();
+        let shader = preprocessor.create_shader_module(&device, "Shader", "shader.wgsl");
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -173,10 +139,8 @@ impl WgpuApp {
             cache: None,
         });
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Challenge Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("challenge.wgsl").into()),
-        });
+        let shader =
+            preprocessor.create_shader_module(&device, "Challenge Shader", "challenge.wgsl");
 
         let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -197,14 +161,7 @@ impl WgpuApp {
                 module: &shader,
                 entry_point: Some("vs_main"),
                 compilation_options: Default::default(),
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: core::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![
-                        0 => Float32x3,
-                        1 => Float32x4
-                    ],
-                }],
+                buffers: &[Vertex::LAYOUT],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
@@ -238,7 +195,6 @@ impl WgpuApp {
         });
 
         Self {
-            window,
             surface,
             device,
             queue,
@@ -254,23 +210,85 @@ impl WgpuApp {
         }
     }
 
-    fn set_windows_size(&mut self, size: winit::dpi::PhysicalSize<u32>) {
-        if self.size != size {
-            self.size = size;
-            self.size_changed = true;
-        }
-    }
-
     fn resize(&mut self) {
         if self.size_changed {
             self.size_changed = false;
             self.config.width = self.size.width;
             self.config.height = self.size.height;
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = self.surface.as_ref() {
+                surface.configure(&self.device, &self.config);
+            }
         }
     }
+}
+
+impl WgpuAppAction for WgpuApp {
+    async fn new(window: Arc<Window>) -> Self {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let surface = instance.create_surface(window.clone()).unwrap();
 
-    fn keyboard_input(&mut self, event: &winit::event::KeyEvent) {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .unwrap();
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                required_limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    wgpu::Limits::default()
+                },
+                memory_hints: wgpu::MemoryHints::Performance,
+                trace: wgpu::Trace::Off,
+            })
+            .await
+            .unwrap();
+
+        let mut size = window.inner_size();
+        size.width = size.width.max(1);
+        size.height = size.height.max(1);
+        let caps = surface.get_capabilities(&adapter);
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: caps.formats[0],
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+        surface.configure(&device, &config);
+
+        Self::create(Some(surface), device, queue, config)
+    }
+
+    async fn new_headless(ctx: utils::headless::HeadlessContext) -> Self {
+        Self::create(None, ctx.device, ctx.queue, ctx.config)
+    }
+
+    fn set_window_size(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        if self.size != size {
+            self.size = size;
+            self.size_changed = true;
+        }
+    }
+
+    fn get_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    fn keyboard_input(&mut self, event: &winit::event::KeyEvent) -> bool {
         if event.physical_key == PhysicalKey::Code(winit::keyboard::KeyCode::Enter) {
             self.use_color = event.state == event::ElementState::Released;
             self.clear_color = if event.state == event::ElementState::Pressed {
@@ -282,8 +300,10 @@ impl WgpuApp {
                     b: 0.3,
                     a: 1.0,
                 }
-            }
+            };
+            return true;
         }
+        false
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -292,11 +312,21 @@ impl WgpuApp {
         }
         self.resize();
 
-        let output = self.surface.get_current_texture()?;
+        let Some(surface) = self.surface.as_ref() else {
+            return Ok(());
+        };
+        let output = surface.get_current_texture()?;
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        self.render_to_view(&view);
+        output.present();
+
+        Ok(())
+    }
+
+    fn render_to_view(&mut self, view: &wgpu::TextureView) {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -307,7 +337,7 @@ impl WgpuApp {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     depth_slice: None,
                     resolve_target: None,
                     ops: wgpu::Operations {
@@ -331,64 +361,38 @@ impl WgpuApp {
         }
 
         self.queue.submit(Some(encoder.finish()));
-        output.present();
-
-        Ok(())
     }
 }
 
-#[derive(Default)]
-struct WgpuAppHandler {
-    app: Arc<Mutex<Option<WgpuApp>>>,
-}
+#[cfg(test)]
+mod tests {
+    use utils::shader_check::ShaderCheck;
 
-impl ApplicationHandler for WgpuAppHandler {
-    fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        if self.app.as_ref().lock().is_some() {
-            return;
-        }
+    use super::{Vertex, WgpuApp, preprocessor};
 
-        let window_attributes = Window::default_attributes().with_title("WGPU App");
-        let window = event_loop.create_window(window_attributes).unwrap();
+    #[test]
+    fn shaders_match_pipelines() {
+        let shader = ShaderCheck::new(&preprocessor(), "shader.wgsl").unwrap();
+        shader.vertex_buffers("vs_main", &[]).unwrap();
+        shader.bind_groups(&["vs_main", "fs_main"], &[]).unwrap();
 
-        let wgpu_app: WgpuApp = pollster::block_on(WgpuApp::new(Arc::new(window)));
-
-        self.app.lock().replace(wgpu_app);
+        let challenge = ShaderCheck::new(&preprocessor(), "challenge.wgsl").unwrap();
+        challenge
+            .vertex_buffers("vs_main", &[Vertex::LAYOUT])
+            .unwrap();
+        challenge.bind_groups(&["vs_main", "fs_main"], &[]).unwrap();
     }
 
-    fn window_event(
-        &mut self,
-        event_loop: &winit::event_loop::ActiveEventLoop,
-        _window_id: winit::window::WindowId,
-        event: winit::event::WindowEvent,
-    ) {
-        let mut app = self.app.lock();
-
-        let app = app.as_mut().unwrap();
-        match event {
-            winit::event::WindowEvent::CloseRequested => event_loop.exit(),
-            winit::event::WindowEvent::Resized(size) => {
-                if size.width == 0 || size.height == 0 {
-                    log::info!("Window minimized!");
-                } else {
-                    log::info!("Window resized: {:?}", size);
-
-                    app.set_windows_size(size);
-                }
-            }
-            winit::event::WindowEvent::RedrawRequested => {
-                app.window.pre_present_notify();
-
-                match app.render() {
-                    Ok(_) => {}
-                    Err(wgpu::SurfaceError::Lost) => eprintln!("Surface lost, resizing..."),
-                    Err(e) => eprintln!("Surface error: {:?}", e),
-                }
+    #[test]
+    fn triangle_matches_golden() {
+        utils::assert_golden!(WgpuApp, "triangle");
+    }
 
-                app.window.request_redraw();
-            }
-            winit::event::WindowEvent::KeyboardInput { event, .. } => app.keyboard_input(&event),
-            _ => {}
-        }
+    #[test]
+    fn pentagon_matches_golden() {
+        utils::assert_golden!(WgpuApp, "pentagon", |app: &mut WgpuApp| {
+            app.use_color = false;
+            app.clear_color = wgpu::Color::BLACK;
+        });
     }
 }
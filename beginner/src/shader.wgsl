@@ -1,6 +1,4 @@
-struct VertexOutput {
-    @builtin(position) clip_position: vec4f,
-};
+#include "common.wgsl"
 
 @vertex
 fn vs_main(
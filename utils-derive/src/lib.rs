@@ -0,0 +1,138 @@
+//! `utils` 的派生宏，通过 `utils::vertex::VertexLayout` 使用。
+//!
+//! 生成的代码只通过 `::utils::__private` 引用依赖，使用派生宏的 crate 不需要直接依赖 `wgpu`。
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Fields, Ident, LitInt, Token, parse_macro_input, punctuated::Punctuated,
+};
+
+/// 根据字段类型生成顶点缓冲布局
+///
+/// 生成 `ATTRIBUTES` 和 `LAYOUT` 两个常量。字段依次占用从 0 开始的 location，
+/// 矩阵等类型占用多个连续的 location；`#[location(n)]` 让字段从 `n` 开始，
+/// 之后的字段接着编号。结构体上的 `#[step_mode(Instance)]` 用于实例缓冲。
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
+/// #[step_mode(Instance)]
+/// struct InstanceRaw {
+///     #[location(5)]
+///     model: [[f32; 4]; 4],
+///     tint: [f32; 4],
+/// }
+/// ```
+#[proc_macro_derive(VertexLayout, attributes(location, step_mode))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "VertexLayout cannot be derived for generic structs",
+        ));
+    }
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "VertexLayout can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &data.fields,
+            "VertexLayout requires named fields",
+        ));
+    };
+
+    // 顶点数据直接拷贝到 GPU，字段顺序和偏移必须固定
+    let repr_c = input.attrs.iter().any(|attr| {
+        attr.path().is_ident("repr")
+            && attr
+                .parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)
+                .is_ok_and(|reprs| reprs.iter().any(|repr| repr == "C"))
+    });
+    if !repr_c {
+        return Err(syn::Error::new_spanned(
+            name,
+            "VertexLayout requires #[repr(C)]",
+        ));
+    }
+
+    let mut step_mode = quote!(Vertex);
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("step_mode"))
+    {
+        let mode: Ident = attr.parse_args()?;
+        if mode != "Vertex" && mode != "Instance" {
+            return Err(syn::Error::new_spanned(
+                mode,
+                "expected #[step_mode(Vertex)] or #[step_mode(Instance)]",
+            ));
+        }
+        step_mode = quote!(#mode);
+    }
+
+    let fields = fields
+        .named
+        .iter()
+        .map(|field| {
+            let ident = &field.ident;
+            let ty = &field.ty;
+            let mut location = quote!(None);
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("location"))
+            {
+                let value: LitInt = attr.parse_args()?;
+                value.base10_parse::<u32>()?;
+                location = quote!(Some(#value));
+            }
+            Ok(quote! {
+                ::utils::__private::Field {
+                    formats: <#ty as ::utils::__private::VertexField>::FORMATS,
+                    offset: ::core::mem::offset_of!(#name, #ident)
+                        as ::utils::__private::wgpu::BufferAddress,
+                    location: #location,
+                }
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #name {
+            #[doc(hidden)]
+            const VERTEX_FIELDS: &'static [::utils::__private::Field] = &[#(#fields),*];
+
+            pub const ATTRIBUTES: [
+                ::utils::__private::wgpu::VertexAttribute;
+                ::utils::__private::attribute_count(#name::VERTEX_FIELDS)
+            ] = ::utils::__private::attributes(#name::VERTEX_FIELDS);
+
+            pub const LAYOUT: ::utils::__private::wgpu::VertexBufferLayout<'static> =
+                ::utils::__private::wgpu::VertexBufferLayout {
+                    array_stride: ::core::mem::size_of::<#name>()
+                        as ::utils::__private::wgpu::BufferAddress,
+                    step_mode: ::utils::__private::wgpu::VertexStepMode::#step_mode,
+                    attributes: &#name::ATTRIBUTES,
+                };
+        }
+
+        // 即使没有用到 `LAYOUT`，重复的 location 也在派生时报错
+        const _: () = {
+            let _attributes = #name::ATTRIBUTES;
+        };
+    })
+}
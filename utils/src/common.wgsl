@@ -0,0 +1,30 @@
+// 各个示例共用的顶点着色器输入输出，在引入之前用 #define 选择顶点携带的数据：
+// VERTEX_COLOR 为顶点颜色，VERTEX_TEX_COORDS 为纹理坐标，都没有定义时只有裁剪空间位置
+
+#ifdef VERTEX_COLOR
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) color: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) color: vec4f,
+};
+#else
+#ifdef VERTEX_TEX_COORDS
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) tex_coords: vec2f,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) tex_coords: vec2f,
+};
+#else
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+};
+#endif
+#endif
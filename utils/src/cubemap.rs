@@ -0,0 +1,286 @@
+use std::f32::consts::PI;
+use std::path::Path;
+
+use glam::{Vec2, Vec3};
+
+use crate::mipmap::MipmapGenerator;
+use crate::texture::TextureError;
+
+/// 六个面按 +X、-X、+Y、-Y、+Z、-Z 的顺序排列，与纹理数组的层对应
+pub const FACE_NAMES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+/// 立方体贴图，用于天空盒和基于图像的光照
+///
+/// 带有完整的 mip 链，粗糙的表面可以从较高的 mip 级别采样模糊的反射
+pub struct CubeTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    /// 每个面的边长
+    pub size: u32,
+}
+
+impl CubeTexture {
+    /// 线性 HDR 格式，可以保存大于 1 的亮度
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    /// 对每个纹素中心的方向调用 `f` 生成立方体贴图，`f` 返回线性颜色
+    pub fn from_fn(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        size: u32,
+        f: impl Fn(Vec3) -> Vec3,
+    ) -> Self {
+        let faces: Vec<Vec<Vec3>> = (0..6)
+            .map(|face| {
+                (0..size * size)
+                    .map(|i| {
+                        let (x, y) = (i % size, i / size);
+                        let uv = (Vec2::new(x as f32, y as f32) + 0.5) / size as f32 * 2.0 - 1.0;
+                        f(face_direction(face, uv.x, uv.y))
+                    })
+                    .collect()
+            })
+            .collect();
+        Self::from_texels(device, queue, mipmaps, size, &faces)
+    }
+
+    /// 从六张同样大小的正方形图片创建，整数格式的图片视为 sRGB 编码
+    pub fn from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        faces: &[image::DynamicImage; 6],
+    ) -> Result<Self, TextureError> {
+        let size = faces[0].width();
+        let mut texels = Vec::with_capacity(6);
+        for (face, image) in faces.iter().enumerate() {
+            if image.width() != size || image.height() != size {
+                return Err(TextureError::InvalidCubeFace {
+                    face,
+                    width: image.width(),
+                    height: image.height(),
+                });
+            }
+
+            let is_float = matches!(
+                image,
+                image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_)
+            );
+            texels.push(
+                image
+                    .to_rgb32f()
+                    .pixels()
+                    .map(|p| {
+                        let color = Vec3::from(p.0);
+                        if is_float {
+                            color
+                        } else {
+                            srgb_to_linear(color)
+                        }
+                    })
+                    .collect(),
+            );
+        }
+        Ok(Self::from_texels(device, queue, mipmaps, size, &texels))
+    }
+
+    /// 从目录中读取 `px.png`、`nx.png` 等六张图片，扩展名由 `extension` 指定
+    pub fn from_face_paths(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        dir: impl AsRef<Path>,
+        extension: &str,
+    ) -> Result<Self, TextureError> {
+        let dir = dir.as_ref();
+        let mut faces = Vec::with_capacity(6);
+        for name in FACE_NAMES {
+            faces.push(image::open(dir.join(format!("{name}.{extension}")))?);
+        }
+        let faces: [image::DynamicImage; 6] = faces.try_into().unwrap();
+        Self::from_faces(device, queue, mipmaps, &faces)
+    }
+
+    /// 把等距柱状投影的全景图重新采样到边长为 `size` 的立方体贴图
+    pub fn from_equirect(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        image: &image::Rgb32FImage,
+        size: u32,
+    ) -> Self {
+        Self::from_fn(device, queue, mipmaps, size, |dir| {
+            sample_bilinear(image, equirect_uv(dir))
+        })
+    }
+
+    /// 读取 `.hdr` 等格式的全景图，面的边长取图片高度的一半
+    pub fn from_equirect_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, TextureError> {
+        let image = image::open(path)?.to_rgb32f();
+        let size = (image.height() / 2).max(1);
+        Ok(Self::from_equirect(device, queue, mipmaps, &image, size))
+    }
+
+    /// mip 级别的数量，着色器按粗糙度选择级别时需要
+    pub fn mip_level_count(&self) -> u32 {
+        self.texture.mip_level_count()
+    }
+
+    fn from_texels(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        size: u32,
+        faces: &[Vec<Vec3>],
+    ) -> Self {
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Cube Texture"),
+            size: extent,
+            mip_level_count: extent.max_mips(wgpu::TextureDimension::D2),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        let data: Vec<u16> = faces
+            .iter()
+            .flatten()
+            .flat_map(|color| {
+                [color.x, color.y, color.z, 1.0].map(|c| half::f16::from_f32(c).to_bits())
+            })
+            .collect();
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&data),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(8 * size),
+                rows_per_image: Some(size),
+            },
+            extent,
+        );
+
+        mipmaps.generate(device, queue, &texture);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Cube Texture View"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Cube Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            size,
+        }
+    }
+}
+
+/// 立方体贴图第 `face` 个面上 `(u, v)` 处的方向（未归一化），`u`、`v` 在 `[-1, 1]` 内，
+/// `v` 向下增大，与纹理的行顺序一致
+pub fn face_direction(face: usize, u: f32, v: f32) -> Vec3 {
+    match face {
+        0 => Vec3::new(1.0, -v, -u),
+        1 => Vec3::new(-1.0, -v, u),
+        2 => Vec3::new(u, 1.0, v),
+        3 => Vec3::new(u, -1.0, -v),
+        4 => Vec3::new(u, -v, 1.0),
+        5 => Vec3::new(-u, -v, -1.0),
+        _ => panic!("cube face index out of range: {face}"),
+    }
+}
+
+/// 方向在等距柱状投影全景图上的纹理坐标，-Z 方向位于图片中央，+Y 在顶部
+pub fn equirect_uv(dir: Vec3) -> Vec2 {
+    let dir = dir.normalize();
+    Vec2::new(
+        0.5 + dir.x.atan2(-dir.z) / (2.0 * PI),
+        dir.y.clamp(-1.0, 1.0).acos() / PI,
+    )
+}
+
+/// 水平方向环绕、垂直方向截断的双线性采样
+fn sample_bilinear(image: &image::Rgb32FImage, uv: Vec2) -> Vec3 {
+    let (width, height) = image.dimensions();
+    let x = uv.x * width as f32 - 0.5;
+    let y = (uv.y * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+
+    let texel = |x: i64, y: i64| {
+        let x = x.rem_euclid(width as i64) as u32;
+        let y = y.clamp(0, height as i64 - 1) as u32;
+        Vec3::from(image.get_pixel(x, y).0)
+    };
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let top = texel(x0, y0).lerp(texel(x0 + 1, y0), tx);
+    let bottom = texel(x0, y0 + 1).lerp(texel(x0 + 1, y0 + 1), tx);
+    top.lerp(bottom, ty)
+}
+
+fn srgb_to_linear(color: Vec3) -> Vec3 {
+    Vec3::from(color.to_array().map(|c| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_centers_point_along_axes() {
+        let axes = [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z];
+        for (face, axis) in axes.into_iter().enumerate() {
+            assert_eq!(face_direction(face, 0.0, 0.0), axis);
+        }
+        // +Z 面的右上角朝向 +X、+Y
+        assert_eq!(face_direction(4, 1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn equirect_uv_maps_poles_and_forward() {
+        assert!((equirect_uv(-Vec3::Z) - Vec2::new(0.5, 0.5)).length() < 1e-6);
+        assert!((equirect_uv(Vec3::X) - Vec2::new(0.75, 0.5)).length() < 1e-6);
+        assert!(equirect_uv(Vec3::Y).y.abs() < 1e-6);
+        assert!((equirect_uv(-Vec3::Y).y - 1.0).abs() < 1e-6);
+    }
+}
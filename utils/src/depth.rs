@@ -0,0 +1,203 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+
+/// 深度缓冲中的值与视图空间距离的关系，可视化时按它把深度还原成距离
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepthMapping {
+    /// 透视投影，近平面深度为 0，远平面为 1，深度与距离成反比
+    Perspective { near: f32, far: f32 },
+    /// 正交投影，深度本身就与距离成线性关系
+    Orthographic { near: f32, far: f32 },
+    /// 无限远平面 + 反向 Z：近平面深度为 1，无穷远处为 0。
+    /// 没有远平面，`far` 只用于显示，这个距离及更远处显示为白色
+    InfiniteReverseZ { near: f32, far: f32 },
+}
+
+/// 与 `depth.wgsl` 中的 `DepthRange` 一一对应
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct DepthRange {
+    near: f32,
+    far: f32,
+    /// 0 为透视投影，1 为正交投影，2 为无限远反向 Z
+    kind: u32,
+    _padding: u32,
+}
+
+impl From<DepthMapping> for DepthRange {
+    fn from(mapping: DepthMapping) -> Self {
+        let (near, far, kind) = match mapping {
+            DepthMapping::Perspective { near, far } => (near, far, 0),
+            DepthMapping::Orthographic { near, far } => (near, far, 1),
+            DepthMapping::InfiniteReverseZ { near, far } => (near, far, 2),
+        };
+        Self {
+            near,
+            far,
+            kind,
+            _padding: 0,
+        }
+    }
+}
+
+/// 调试用的深度缓冲可视化：用全屏三角形把深度纹理绘制到颜色目标上
+pub struct DepthDebug {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    range_buffer: wgpu::Buffer,
+}
+
+impl DepthDebug {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_texture: &Texture,
+        mapping: DepthMapping,
+    ) -> Self {
+        let range_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Depth Range Buffer"),
+            contents: bytemuck::cast_slice(&[DepthRange::from(mapping)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Depth Debug Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group =
+            Self::create_bind_group(device, &bind_group_layout, &range_buffer, depth_texture);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Depth Debug Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("depth.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth Debug Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Depth Debug Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            range_buffer,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        range_buffer: &wgpu::Buffer,
+        depth_texture: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Depth Debug Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: range_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&depth_texture.view),
+                },
+            ],
+        })
+    }
+
+    /// 深度纹理重新创建后需要重新绑定
+    pub fn resize(&mut self, device: &wgpu::Device, depth_texture: &Texture) {
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.range_buffer,
+            depth_texture,
+        );
+    }
+
+    /// 相机的投影方式或近远平面变化时同步更新，用于把深度还原成线性距离
+    pub fn set_mapping(&self, queue: &wgpu::Queue, mapping: DepthMapping) {
+        queue.write_buffer(
+            &self.range_buffer,
+            0,
+            bytemuck::cast_slice(&[DepthRange::from(mapping)]),
+        );
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Depth Debug Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,50 @@
+// 把深度缓冲线性化后以灰度显示，近处为黑色，远处为白色
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+};
+
+// kind: 0 为透视投影，1 为正交投影，2 为无限远反向 Z
+struct DepthRange {
+    near: f32,
+    far: f32,
+    kind: u32,
+    _padding: u32,
+}
+@group(0) @binding(0)
+var<uniform> range: DepthRange;
+@group(0) @binding(1)
+var t_depth: texture_2d<f32>;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2f(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    out.clip_position = vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let depth = textureLoad(t_depth, vec2i(in.clip_position.xy), 0).r;
+    var shade: f32;
+    switch range.kind {
+        case 1u: {
+            // 正交投影的深度已经是 (距离 - near) / (far - near)
+            shade = depth;
+        }
+        case 2u: {
+            // 深度为 near / 距离，无穷远处为 0，far 只是显示用的范围
+            let linear = range.near / max(depth, 1e-7);
+            shade = clamp((linear - range.near) / (range.far - range.near), 0.0, 1.0);
+        }
+        default: {
+            // 右手坐标系透视投影，深度范围 [0, 1]
+            let linear = range.near * range.far / (range.far - depth * (range.far - range.near));
+            shade = (linear - range.near) / (range.far - range.near);
+        }
+    }
+    return vec4f(vec3f(shade), 1.0);
+}
@@ -15,9 +15,15 @@ use winit::{
 #[cfg(target_arch = "wasm32")]
 use winit::platform::web::WindowExtWebSys;
 
+use crate::headless::HeadlessContext;
+
 pub trait WgpuAppAction {
     fn new(window: Arc<Window>) -> impl core::future::Future<Output = Self> + WasmNotSend;
 
+    /// 不创建窗口，直接基于离屏渲染目标的上下文创建应用
+    fn new_headless(ctx: HeadlessContext)
+    -> impl core::future::Future<Output = Self> + WasmNotSend;
+
     fn set_window_size(&mut self, new_size: PhysicalSize<u32>);
 
     fn get_size(&self) -> PhysicalSize<u32>;
@@ -42,7 +48,13 @@ pub trait WgpuAppAction {
         false
     }
 
+    /// 每帧渲染之前调用，用于更新相机、uniform 等状态
+    fn update(&mut self) {}
+
     fn render(&mut self) -> Result<(), wgpu::SurfaceError>;
+
+    /// 将一帧绘制到给定的纹理视图上，无头模式下由 `headless::run_headless` 调用
+    fn render_to_view(&mut self, view: &wgpu::TextureView);
 }
 
 struct WgpuAppHandler<A> {
@@ -184,7 +196,21 @@ impl<A: WgpuAppAction + 'static> ApplicationHandler for WgpuAppHandler<A> {
                     app.set_window_size(physical_size);
                 }
             }
+            WindowEvent::KeyboardInput { event, .. } => {
+                app.keyboard_input(&event);
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                app.mouse_click(state, button);
+            }
+            WindowEvent::MouseWheel { delta, phase, .. } => {
+                app.mouse_wheel(delta, phase);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                app.cursor_move(position);
+            }
             WindowEvent::RedrawRequested => {
+                app.update();
+
                 self.pre_present_notify();
                 match app.render() {
                     Ok(_) => {}
@@ -200,6 +226,18 @@ impl<A: WgpuAppAction + 'static> ApplicationHandler for WgpuAppHandler<A> {
             _ => {}
         }
     }
+
+    /// 鼠标锁定后窗口不再收到光标移动，转动视角需要原始的 `DeviceEvent::MouseMotion`
+    fn device_event(
+        &mut self,
+        _event_loop: &winit::event_loop::ActiveEventLoop,
+        _device_id: winit::event::DeviceId,
+        event: DeviceEvent,
+    ) {
+        if let Some(app) = self.app.lock().as_mut() {
+            app.device_input(&event);
+        }
+    }
 }
 
 pub fn run<A: WgpuAppAction + 'static>(title: &'static str) -> Result<(), impl std::error::Error> {
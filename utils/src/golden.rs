@@ -0,0 +1,317 @@
+//! 基于参考图片的回归测试工具。
+//!
+//! 设置环境变量 `UPDATE_GOLDEN=1`（或 `true`）运行测试会用当前渲染结果覆盖参考图片；
+//! 比对失败时会在参考图片旁边写出 `*.actual.png` 和 `*.diff.png` 方便排查，比对通过后删除它们。
+//! 没有 GPU 的环境需要显式设置 `SKIP_GPU_TESTS=1` 才会跳过测试，否则测试失败。
+
+use std::{
+    ffi::OsStr,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use image::{Rgba, RgbaImage};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    framework::WgpuAppAction,
+    headless::{Frame, HeadlessApp, require_adapter},
+};
+
+/// 参考图片使用的统一尺寸
+pub const GOLDEN_SIZE: PhysicalSize<u32> = PhysicalSize::new(256, 256);
+
+/// 比对参考图片时允许的误差
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// 单个通道允许的最大差值，超过则认为该像素不匹配
+    pub per_channel: u8,
+    /// 允许不匹配的像素占比，用于容忍不同适配器在三角形边缘光栅化的差异
+    pub max_mismatched_ratio: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            per_channel: 2,
+            max_mismatched_ratio: 0.005,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Image(image::ImageError),
+    Missing(PathBuf),
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    Mismatch {
+        mismatched: usize,
+        total: usize,
+        max_difference: u8,
+        diff: PathBuf,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Image(e) => write!(f, "image error: {e}"),
+            GoldenError::Missing(path) => write!(
+                f,
+                "reference {} does not exist, rerun with UPDATE_GOLDEN=1 to create it",
+                path.display()
+            ),
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "size mismatch: expected {}x{}, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            GoldenError::Mismatch {
+                mismatched,
+                total,
+                max_difference,
+                diff,
+            } => write!(
+                f,
+                "{mismatched}/{total} pixels differ (max channel difference {max_difference}), see {}",
+                diff.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<image::ImageError> for GoldenError {
+    fn from(e: image::ImageError) -> Self {
+        GoldenError::Image(e)
+    }
+}
+
+/// 逐像素比对的结果
+pub struct Comparison {
+    pub mismatched: usize,
+    pub max_difference: u8,
+    /// 不匹配的像素标红，其余像素按参考图变暗显示
+    pub diff: RgbaImage,
+}
+
+pub fn compare(
+    actual: &RgbaImage,
+    expected: &RgbaImage,
+    tolerance: Tolerance,
+) -> Result<Comparison, GoldenError> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(GoldenError::SizeMismatch {
+            expected: expected.dimensions(),
+            actual: actual.dimensions(),
+        });
+    }
+
+    let mut mismatched = 0;
+    let mut max_difference = 0;
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+
+    for ((a, e), d) in actual
+        .pixels()
+        .zip(expected.pixels())
+        .zip(diff.pixels_mut())
+    {
+        let difference =
+            a.0.iter()
+                .zip(e.0)
+                .map(|(a, e)| a.abs_diff(e))
+                .max()
+                .unwrap();
+        max_difference = max_difference.max(difference);
+
+        *d = if difference > tolerance.per_channel {
+            mismatched += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            Rgba([e[0] / 4, e[1] / 4, e[2] / 4, 255])
+        };
+    }
+
+    Ok(Comparison {
+        mismatched,
+        max_difference,
+        diff,
+    })
+}
+
+/// 覆盖参考图片的环境变量
+pub const UPDATE_GOLDEN: &str = "UPDATE_GOLDEN";
+
+/// 只有 `1` 和 `true` 表示开启，`UPDATE_GOLDEN=0` 或空值不会覆盖参考图片
+fn update_requested(value: Option<&OsStr>) -> bool {
+    value
+        .and_then(OsStr::to_str)
+        .is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+}
+
+/// 删除之前比对失败时留下的 `*.actual.png` 和 `*.diff.png`
+fn remove_stale_outputs(reference: &Path) -> Result<(), GoldenError> {
+    for extension in ["actual.png", "diff.png"] {
+        match std::fs::remove_file(reference.with_extension(extension)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(image::ImageError::IoError(e).into());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 将渲染结果与参考图片比对，设置了 [`UPDATE_GOLDEN`] 时直接用渲染结果覆盖参考图片
+pub fn check(
+    reference: impl AsRef<Path>,
+    frame: &Frame,
+    tolerance: Tolerance,
+) -> Result<(), GoldenError> {
+    let reference = reference.as_ref();
+    let actual = frame.to_image();
+
+    if update_requested(std::env::var_os(UPDATE_GOLDEN).as_deref()) {
+        if let Some(dir) = reference.parent() {
+            std::fs::create_dir_all(dir).map_err(image::ImageError::IoError)?;
+        }
+        actual.save(reference)?;
+        log::warn!("Golden image written: {}", reference.display());
+        return remove_stale_outputs(reference);
+    }
+
+    if !reference.exists() {
+        actual.save(reference.with_extension("actual.png"))?;
+        return Err(GoldenError::Missing(reference.to_path_buf()));
+    }
+
+    let expected = image::open(reference)?.to_rgba8();
+    let comparison = compare(&actual, &expected, tolerance)?;
+
+    let total = actual.pixels().len();
+    if comparison.mismatched as f32 > total as f32 * tolerance.max_mismatched_ratio {
+        let diff = reference.with_extension("diff.png");
+        actual.save(reference.with_extension("actual.png"))?;
+        comparison.diff.save(&diff)?;
+
+        return Err(GoldenError::Mismatch {
+            mismatched: comparison.mismatched,
+            total,
+            max_difference: comparison.max_difference,
+            diff,
+        });
+    }
+
+    remove_stale_outputs(reference)
+}
+
+/// 在无头模式下渲染应用，`setup` 可以在渲染前调整应用状态。
+///
+/// 没有可用的适配器时按 [`require_adapter`] 处理：默认测试失败，
+/// 设置了 `SKIP_GPU_TESTS=1` 时返回 `None` 跳过测试。
+pub fn render<A: WgpuAppAction>(
+    size: PhysicalSize<u32>,
+    frames: u32,
+    setup: impl FnOnce(&mut A),
+) -> Option<Frame> {
+    pollster::block_on(async {
+        let mut headless = require_adapter(HeadlessApp::<A>::new(size).await, "golden test")?;
+
+        setup(&mut headless.app);
+        headless.render_frames(frames);
+        Some(headless.read_pixels().await.expect("read back frame"))
+    })
+}
+
+/// 渲染并与 `<crate>/tests/golden/<name>.png` 比对，不匹配时 panic
+#[macro_export]
+macro_rules! assert_golden {
+    ($app:ty, $name:expr) => {
+        $crate::assert_golden!($app, $name, |_| {})
+    };
+    ($app:ty, $name:expr, $setup:expr) => {
+        $crate::assert_golden!($app, $name, $setup, $crate::golden::Tolerance::default())
+    };
+    ($app:ty, $name:expr, $setup:expr, $tolerance:expr) => {{
+        let reference = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.png", $name));
+        if let Some(frame) = $crate::golden::render::<$app>($crate::golden::GOLDEN_SIZE, 1, $setup)
+        {
+            if let Err(e) = $crate::golden::check(&reference, &frame, $tolerance) {
+                panic!("golden image `{}` mismatch: {e}", $name);
+            }
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_respects_per_channel_tolerance() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, Rgba([102, 100, 100, 255]));
+        actual.put_pixel(1, 0, Rgba([110, 100, 100, 255]));
+
+        let tolerance = Tolerance {
+            per_channel: 2,
+            max_mismatched_ratio: 0.0,
+        };
+        let comparison = compare(&actual, &expected, tolerance).unwrap();
+
+        assert_eq!(comparison.mismatched, 1);
+        assert_eq!(comparison.max_difference, 10);
+        assert_eq!(*comparison.diff.get_pixel(1, 0), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn compare_rejects_different_sizes() {
+        let expected = RgbaImage::new(4, 4);
+        let actual = RgbaImage::new(2, 4);
+
+        assert!(matches!(
+            compare(&actual, &expected, Tolerance::default()),
+            Err(GoldenError::SizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn update_golden_accepts_only_one_or_true() {
+        for value in ["1", "true", "TRUE"] {
+            assert!(update_requested(Some(OsStr::new(value))), "{value}");
+        }
+        for value in ["0", "false", "", "yes"] {
+            assert!(!update_requested(Some(OsStr::new(value))), "{value}");
+        }
+        assert!(!update_requested(None));
+    }
+
+    #[test]
+    fn passing_check_removes_stale_outputs() {
+        let dir = crate::headless::TempDir::new("utils-golden");
+        let reference = dir.join("frame.png");
+        let frame = Frame {
+            width: 2,
+            height: 2,
+            pixels: vec![255; 16],
+        };
+        frame.to_image().save(&reference).unwrap();
+        let actual = reference.with_extension("actual.png");
+        let diff = reference.with_extension("diff.png");
+        RgbaImage::new(2, 2).save(&actual).unwrap();
+        RgbaImage::new(2, 2).save(&diff).unwrap();
+
+        check(&reference, &frame, Tolerance::default()).unwrap();
+
+        assert!(reference.exists());
+        assert!(!actual.exists());
+        assert!(!diff.exists());
+    }
+}
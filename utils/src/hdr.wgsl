@@ -0,0 +1,105 @@
+// 对 HDR 纹理做曝光和色调映射，写入 LDR 输出
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+};
+
+struct HdrParams {
+    // 线性的曝光倍数，即 2^EV
+    exposure: f32,
+    tonemap: u32,
+    // 输出格式不是 sRGB 时需要手动编码
+    encode_srgb: u32,
+}
+@group(0) @binding(0)
+var<uniform> params: HdrParams;
+@group(0) @binding(1)
+var t_hdr: texture_2d<f32>;
+
+const TONEMAP_REINHARD: u32 = 0;
+const TONEMAP_ACES: u32 = 1;
+const TONEMAP_AGX: u32 = 2;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2f(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    out.clip_position = vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+fn tonemap_reinhard(color: vec3f) -> vec3f {
+    return color / (1.0 + color);
+}
+
+// Narkowicz 拟合的 ACES 曲线
+fn tonemap_aces(color: vec3f) -> vec3f {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3f(0.0), vec3f(1.0));
+}
+
+// AgX 默认对比度曲线的多项式拟合 (Benjamin Wrensch, "Minimal AgX Implementation")
+fn agx_contrast(x: vec3f) -> vec3f {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn tonemap_agx(color: vec3f) -> vec3f {
+    let inset = mat3x3f(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    );
+    let outset = mat3x3f(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    var x = inset * color;
+    x = clamp(log2(max(x, vec3f(1e-10))), vec3f(min_ev), vec3f(max_ev));
+    x = (x - min_ev) / (max_ev - min_ev);
+    x = agx_contrast(x);
+    x = outset * x;
+    // 曲线的输出已经是显示编码，转回线性空间与其他算子保持一致
+    return pow(max(x, vec3f(0.0)), vec3f(2.2));
+}
+
+fn linear_to_srgb(color: vec3f) -> vec3f {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3f(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3f(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let hdr = textureLoad(t_hdr, vec2i(in.clip_position.xy), 0);
+    let exposed = hdr.rgb * params.exposure;
+
+    var color: vec3f;
+    switch params.tonemap {
+        case TONEMAP_REINHARD: {
+            color = tonemap_reinhard(exposed);
+        }
+        case TONEMAP_AGX: {
+            color = tonemap_agx(exposed);
+        }
+        default: {
+            color = tonemap_aces(exposed);
+        }
+    }
+
+    if params.encode_srgb != 0u {
+        color = linear_to_srgb(saturate(color));
+    }
+    return vec4f(color, hdr.a);
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+#[cfg(any(test, feature = "test-utils"))]
+use std::path::{Path, PathBuf};
+
+use winit::dpi::PhysicalSize;
+
+use crate::framework::WgpuAppAction;
+
+/// 离屏渲染目标默认使用的格式，读回 CPU 时每个像素 4 字节 (RGBA)
+pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// 设置后，没有可用适配器的环境会跳过 GPU 测试而不是让它们失败
+pub const SKIP_GPU_TESTS: &str = "SKIP_GPU_TESTS";
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    Adapter(wgpu::RequestAdapterError),
+    Device(wgpu::RequestDeviceError),
+    Poll(wgpu::PollError),
+    Map(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::Adapter(e) => write!(f, "failed to find an adapter: {e}"),
+            HeadlessError::Device(e) => write!(f, "failed to create device: {e}"),
+            HeadlessError::Poll(e) => write!(f, "failed to poll device: {e}"),
+            HeadlessError::Map(e) => write!(f, "failed to map readback buffer: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+/// 不依赖窗口的 wgpu 上下文。
+///
+/// `config` 模拟了窗口模式下的 `SurfaceConfiguration`，这样应用创建管线时
+/// 可以继续使用 `config.format` / `config.width` 这些字段。
+#[derive(Clone)]
+pub struct HeadlessContext {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub config: wgpu::SurfaceConfiguration,
+}
+
+impl HeadlessContext {
+    /// 优先使用硬件适配器，找不到时回退到软件适配器（如 llvmpipe / WARP）
+    pub async fn new(size: PhysicalSize<u32>) -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::from_env().unwrap_or_else(wgpu::Backends::all),
+            ..Default::default()
+        });
+
+        let adapter = match instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+        {
+            Ok(adapter) => adapter,
+            Err(_) => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::LowPower,
+                    compatible_surface: None,
+                    force_fallback_adapter: true,
+                })
+                .await
+                .map_err(HeadlessError::Adapter)?,
+        };
+        log::info!("Headless adapter: {:?}", adapter.get_info());
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("Headless Device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_defaults()
+                    .using_resolution(adapter.limits()),
+                memory_hints: wgpu::MemoryHints::default(),
+                trace: wgpu::Trace::Off,
+            })
+            .await
+            .map_err(HeadlessError::Device)?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: HEADLESS_FORMAT,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            config,
+        })
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.config.width, self.config.height)
+    }
+}
+
+/// 从 GPU 读回的一帧图像，按行紧密排列的 RGBA8 像素
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn to_image(&self) -> image::RgbaImage {
+        image::RgbaImage::from_raw(self.width, self.height, self.pixels.clone())
+            .expect("frame size matches pixel buffer")
+    }
+}
+
+/// 代替交换链纹理的离屏渲染目标
+pub struct OffscreenTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl OffscreenTarget {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self { texture, view }
+    }
+
+    /// 把纹理内容读回 CPU
+    pub async fn read_pixels(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Frame, HeadlessError> {
+        read_texture(device, queue, &self.texture, 0).await
+    }
+}
+
+/// 把每像素 4 字节的纹理的第 `mip_level` 级拷贝到可映射的缓冲区并读回 CPU。
+///
+/// `copy_texture_to_buffer` 要求每行字节数按 256 对齐，读回后再去掉每行的填充。
+pub async fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+) -> Result<Frame, HeadlessError> {
+    let width = (texture.width() >> mip_level).max(1);
+    let height = (texture.height() >> mip_level).max(1);
+    let unpadded_bytes_per_row = 4 * width;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device
+        .poll(wgpu::PollType::Wait)
+        .map_err(HeadlessError::Poll)?;
+    // `poll(Wait)` 返回时回调已经执行完毕
+    receiver
+        .recv()
+        .unwrap_or(Err(wgpu::BufferAsyncError))
+        .map_err(HeadlessError::Map)?;
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    Ok(Frame {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// 运行在离屏目标上的应用，可以在渲染之前直接修改应用状态
+pub struct HeadlessApp<A> {
+    pub ctx: HeadlessContext,
+    pub target: OffscreenTarget,
+    pub app: A,
+}
+
+impl<A: WgpuAppAction> HeadlessApp<A> {
+    pub async fn new(size: PhysicalSize<u32>) -> Result<Self, HeadlessError> {
+        let ctx = HeadlessContext::new(size).await?;
+        let target = OffscreenTarget::new(&ctx.device, &ctx.config);
+        let app = A::new_headless(ctx.clone()).await;
+
+        Ok(Self { ctx, target, app })
+    }
+
+    /// 与窗口模式下的 `RedrawRequested` 一致：先 `update` 再渲染
+    pub fn render_frames(&mut self, frames: u32) {
+        for _ in 0..frames.max(1) {
+            self.app.update();
+            self.app.render_to_view(&self.target.view);
+        }
+    }
+
+    pub async fn read_pixels(&self) -> Result<Frame, HeadlessError> {
+        self.target
+            .read_pixels(&self.ctx.device, &self.ctx.queue)
+            .await
+    }
+}
+
+/// 在离屏目标上创建应用并连续渲染 `frames` 帧，返回最后一帧的像素
+pub async fn run_headless<A: WgpuAppAction>(
+    size: PhysicalSize<u32>,
+    frames: u32,
+) -> Result<Frame, HeadlessError> {
+    let mut headless = HeadlessApp::<A>::new(size).await?;
+    headless.render_frames(frames);
+    headless.read_pixels().await
+}
+
+/// `run_headless` 的阻塞版本，方便在测试和命令行工具中直接调用
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render_app_frames<A: WgpuAppAction>(
+    size: PhysicalSize<u32>,
+    frames: u32,
+) -> Result<Frame, HeadlessError> {
+    pollster::block_on(run_headless::<A>(size, frames))
+}
+
+/// GPU 测试创建上下文或应用的结果
+///
+/// 找不到适配器时默认 panic，避免没有 GPU 的 CI 把测试当作通过；设置了
+/// [`SKIP_GPU_TESTS`] 时打印原因并返回 `None`，调用方据此跳过 `test`
+pub fn require_adapter<T>(result: Result<T, HeadlessError>, test: &str) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e @ HeadlessError::Adapter(_)) if std::env::var_os(SKIP_GPU_TESTS).is_some() => {
+            eprintln!("{SKIP_GPU_TESTS} is set, skipping {test}: {e}");
+            None
+        }
+        Err(e @ HeadlessError::Adapter(_)) => {
+            panic!("{e}; set {SKIP_GPU_TESTS}=1 to skip GPU tests on machines without an adapter")
+        }
+        Err(e) => panic!("{e}"),
+    }
+}
+
+/// 单元测试使用的 1x1 上下文，找不到适配器时按 [`require_adapter`] 处理
+#[cfg(not(target_arch = "wasm32"))]
+pub fn test_context() -> Option<HeadlessContext> {
+    require_adapter(
+        pollster::block_on(HeadlessContext::new(PhysicalSize::new(1, 1))),
+        "GPU test",
+    )
+}
+
+/// 测试用的临时目录，离开作用域时连同其中的文件一起删除
+///
+/// 只在本 crate 的测试中或开启 `test-utils` 特性时可用，其他 crate 在 `dev-dependencies` 中开启
+#[cfg(any(test, feature = "test-utils"))]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl TempDir {
+    /// 目录名带上进程 id，同时运行的多个测试进程不会互相覆盖
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        // 上次运行中断时可能留下了同名目录
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("create temporary directory");
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,399 @@
+//! 开发时的着色器热重载。
+//!
+//! 着色器仍然通过 `include_str!` 编译进程序，调试构建下再监视磁盘上的源文件：
+//! 文件变化后重新读取并用 naga 校验，校验通过才替换着色器模块，
+//! 否则记录错误并继续使用上一次成功的版本。
+//!
+//! 着色器先经过 [`Preprocessor`]，被 `#include` 的文件变化时也会重新加载。
+
+use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use notify::Watcher;
+
+use crate::preprocess::{PreprocessError, Preprocessor, ProcessedShader};
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io(std::io::Error),
+    Preprocess(PreprocessError),
+    /// WGSL 语法或类型错误，内容为带有源码位置的诊断信息
+    Parse(String),
+    /// naga 校验失败
+    Validation(String),
+    /// 设备拒绝创建着色器模块或管线
+    Device(wgpu::Error),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io(e) => write!(f, "failed to read shader: {e}"),
+            ShaderError::Preprocess(e) => write!(f, "failed to preprocess shader: {e}"),
+            ShaderError::Parse(e) => write!(f, "failed to parse shader:\n{e}"),
+            ShaderError::Validation(e) => write!(f, "invalid shader:\n{e}"),
+            ShaderError::Device(e) => write!(f, "device error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl From<std::io::Error> for ShaderError {
+    fn from(e: std::io::Error) -> Self {
+        ShaderError::Io(e)
+    }
+}
+
+impl From<PreprocessError> for ShaderError {
+    fn from(e: PreprocessError) -> Self {
+        ShaderError::Preprocess(e)
+    }
+}
+
+/// 解析并校验预处理后的 WGSL 源码，错误信息中的位置对应预处理之前的文件
+pub fn validate_wgsl(
+    shader: &ProcessedShader,
+) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+    let source = &shader.source;
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| {
+        let location = first_location(e.labels().map(|(span, _)| span), source);
+        ShaderError::Parse(shader.format_error(location, e.message()))
+    })?;
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| {
+        // 校验错误的具体原因在 source 链中
+        let mut message = e.as_inner().to_string();
+        let mut cause = std::error::Error::source(e.as_inner());
+        while let Some(inner) = cause {
+            message.push_str(&format!(": {inner}"));
+            cause = inner.source();
+        }
+        let location = first_location(e.spans().map(|&(span, _)| span), source);
+        ShaderError::Validation(shader.format_error(location, &message))
+    })?;
+    Ok((module, info))
+}
+
+/// 第一个有位置的标签所在的行和列，有些错误的第一个标签没有位置
+fn first_location(mut spans: impl Iterator<Item = naga::Span>, source: &str) -> Option<(u32, u32)> {
+    let location = spans.find(|span| span.is_defined())?.location(source);
+    Some((location.line_number, location.line_position))
+}
+
+/// 在错误作用域中调用 `f`，把设备的校验错误作为 `Err` 返回，而不是触发未捕获错误的回调
+///
+/// 重新创建管线时用它保留上一次成功的管线
+pub fn capture_device_errors<T>(
+    device: &wgpu::Device,
+    f: impl FnOnce() -> T,
+) -> Result<T, ShaderError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = f();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(e) => Err(ShaderError::Device(e)),
+        None => Ok(value),
+    }
+}
+
+/// 可以从磁盘重新加载的着色器模块
+pub struct HotShader {
+    label: String,
+    path: PathBuf,
+    /// 入口文件在预处理器中的名字，`path` 以它结尾
+    name: String,
+    preprocessor: Preprocessor,
+    dependencies: Vec<PathBuf>,
+    module: wgpu::ShaderModule,
+}
+
+impl HotShader {
+    /// 使用编译进程序的 `source` 创建，之后由 [`HotShader::reload`] 从 `path` 重新读取
+    pub fn new(device: &wgpu::Device, label: &str, path: impl Into<PathBuf>, source: &str) -> Self {
+        let path = path.into();
+        let name = path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let preprocessor = Preprocessor::new().with_file(&name, source);
+        Self::with_preprocessor(device, label, path, &name, preprocessor)
+    }
+
+    /// 用 `preprocessor` 中注册的入口文件 `name` 创建，`path` 为它在磁盘上的位置
+    ///
+    /// 编译进程序的着色器预处理失败说明代码有误，会直接 panic
+    pub fn with_preprocessor(
+        device: &wgpu::Device,
+        label: &str,
+        path: impl Into<PathBuf>,
+        name: &str,
+        preprocessor: Preprocessor,
+    ) -> Self {
+        let shader = preprocessor
+            .process(name)
+            .unwrap_or_else(|e| panic!("{label}: {e}"));
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+        });
+        let mut hot_shader = Self {
+            label: label.to_string(),
+            path: path.into(),
+            name: name.to_string(),
+            preprocessor,
+            dependencies: Vec::new(),
+            module,
+        };
+        hot_shader.dependencies = hot_shader.dependencies_of(&shader);
+        hot_shader
+    }
+
+    pub fn module(&self) -> &wgpu::ShaderModule {
+        &self.module
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 入口文件以及它引入的所有文件，其中任何一个变化都需要重新加载
+    pub fn dependencies(&self) -> &[PathBuf] {
+        &self.dependencies
+    }
+
+    /// 预处理器中的文件名都相对于这个目录
+    fn root(&self) -> &Path {
+        self.path
+            .ancestors()
+            .nth(self.name.split('/').count())
+            .unwrap_or(Path::new(""))
+    }
+
+    fn dependencies_of(&self, shader: &ProcessedShader) -> Vec<PathBuf> {
+        let root = self.root();
+        shader.files().iter().map(|file| root.join(file)).collect()
+    }
+
+    /// 重新读取源文件和它引入的文件；失败时保留原来的模块
+    pub fn reload(&mut self, device: &wgpu::Device) -> Result<(), ShaderError> {
+        let shader = self
+            .preprocessor
+            .clone()
+            .with_root(self.root())
+            .process(&self.name)
+            .map_err(|e| match e {
+                PreprocessError::Io { error, .. } => ShaderError::Io(error),
+                e => ShaderError::Preprocess(e),
+            })?;
+        validate_wgsl(&shader)?;
+        self.module = capture_device_errors(device, || {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(&self.label),
+                source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+            })
+        })?;
+        self.dependencies = self.dependencies_of(&shader);
+        Ok(())
+    }
+}
+
+/// 编译进程序并可以热重载的着色器，路径相对于调用方 crate 的 `src` 目录
+///
+/// 可以传入一个注册了被引入文件的预处理器，文件名同样相对于 `src` 目录
+///
+/// ```ignore
+/// let shader = utils::hot_shader!(&device, "Shader", "shader.wgsl");
+/// let shader = utils::hot_shader!(&device, "Shader", "shader.wgsl", &preprocessor);
+/// ```
+#[macro_export]
+macro_rules! hot_shader {
+    ($device:expr, $label:expr, $path:literal) => {
+        $crate::hot_shader!(
+            $device,
+            $label,
+            $path,
+            &$crate::preprocess::Preprocessor::new()
+        )
+    };
+    ($device:expr, $label:expr, $path:literal, $preprocessor:expr) => {
+        $crate::hot_reload::HotShader::with_preprocessor(
+            $device,
+            $label,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/", $path),
+            $path,
+            ::std::clone::Clone::clone($preprocessor).with_file(
+                $path,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/", $path)),
+            ),
+        )
+    };
+}
+
+/// 监视着色器源文件的变化
+///
+/// 只在调试构建中启用；发布构建或者无法创建监视器时，[`ShaderWatcher::changed`] 总是为空
+pub struct ShaderWatcher {
+    watcher: Option<notify::RecommendedWatcher>,
+    receiver: mpsc::Receiver<PathBuf>,
+    /// 被监视的文件，目录中其他文件的变化会被忽略
+    files: HashSet<PathBuf>,
+}
+
+impl ShaderWatcher {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = if cfg!(debug_assertions) {
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                if event.kind.is_modify() || event.kind.is_create() {
+                    for path in event.paths {
+                        let _ = sender.send(path);
+                    }
+                }
+            })
+            .inspect_err(|e| log::warn!("Shader hot reload disabled: {e}"))
+            .ok()
+        } else {
+            None
+        };
+
+        Self {
+            watcher,
+            receiver,
+            files: HashSet::new(),
+        }
+    }
+
+    /// 监视 `path` 所在的目录，编辑器保存时常常先写入临时文件再重命名，直接监视文件会丢失事件
+    pub fn watch(&mut self, path: &Path) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        if !self.files.iter().any(|file| file.parent() == Some(dir))
+            && let Err(e) = watcher.watch(dir, notify::RecursiveMode::NonRecursive)
+        {
+            log::warn!("Failed to watch {}: {e}", dir.display());
+            return;
+        }
+        self.files.insert(path.to_path_buf());
+    }
+
+    /// 上次调用之后发生变化的被监视文件，一次保存产生的多个事件会合并
+    pub fn changed(&self) -> HashSet<PathBuf> {
+        self.receiver
+            .try_iter()
+            .filter(|path| self.files.contains(path))
+            .collect()
+    }
+}
+
+impl Default for ShaderWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::headless::{TempDir, test_context};
+
+    const VALID: &str = "@fragment fn fs_main() -> @location(0) vec4f { return vec4f(1.0); }";
+
+    fn process(files: &[(&str, &str)]) -> ProcessedShader {
+        files
+            .iter()
+            .fold(Preprocessor::new(), |p, (name, source)| {
+                p.with_file(name, source)
+            })
+            .process(files[0].0)
+            .unwrap()
+    }
+
+    #[test]
+    fn valid_wgsl_passes() {
+        assert!(validate_wgsl(&process(&[("valid.wgsl", VALID)])).is_ok());
+    }
+
+    #[test]
+    fn errors_point_at_the_original_file() {
+        let main = "#include \"common.wgsl\"\n@fragment fn fs_main() -> @location(0) vec4f { return f(); }";
+        let common = "// common\nfn f() -> vec4f { return vec4f(1.0) }";
+        match validate_wgsl(&process(&[("main.wgsl", main), ("common.wgsl", common)])) {
+            Err(ShaderError::Parse(message)) => assert!(
+                message.starts_with("common.wgsl:2:") && message.contains("| fn f()"),
+                "{message}"
+            ),
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+
+        let types = "\n@fragment fn fs_main() -> @location(0) vec4f { return 1.0; }";
+        match validate_wgsl(&process(&[("types.wgsl", types)])) {
+            Err(ShaderError::Parse(message) | ShaderError::Validation(message)) => {
+                assert!(message.starts_with("types.wgsl:2:"), "{message}")
+            }
+            other => panic!("expected a type error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn watcher_reports_changed_files() {
+        let dir = TempDir::new("utils-hot-reload");
+        let shader = dir.join("shader.wgsl");
+        let other = dir.join("other.wgsl");
+        std::fs::write(&shader, VALID).unwrap();
+
+        let mut watcher = ShaderWatcher::new();
+        if watcher.watcher.is_none() {
+            return;
+        }
+        watcher.watch(&shader);
+        std::fs::write(&other, VALID).unwrap();
+        std::fs::write(&shader, VALID).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut changed = HashSet::new();
+        while changed.is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+            changed = watcher.changed();
+        }
+        assert_eq!(changed, HashSet::from([shader]));
+    }
+
+    #[test]
+    fn failed_reload_keeps_the_previous_module() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+        let dir = TempDir::new("utils-hot-shader");
+        let path = dir.join("shader.wgsl");
+        let mut shader = HotShader::new(&ctx.device, "Test Shader", &path, VALID);
+        let original = shader.module().clone();
+        assert_eq!(shader.dependencies(), std::slice::from_ref(&path));
+
+        std::fs::write(&path, "fn broken(").unwrap();
+        assert!(matches!(
+            shader.reload(&ctx.device),
+            Err(ShaderError::Parse(_))
+        ));
+        assert_eq!(shader.module(), &original);
+
+        std::fs::write(&path, VALID).unwrap();
+        shader.reload(&ctx.device).unwrap();
+        assert_ne!(shader.module(), &original);
+    }
+}
@@ -1,8 +1,42 @@
 use std::sync::Arc;
+
+// 让派生宏生成的 `::utils::...` 路径在本 crate 中也能解析
+extern crate self as utils;
+
+pub mod cubemap;
+pub mod depth;
 pub mod framework;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod golden;
+pub mod hdr;
+pub mod headless;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod hot_reload;
+pub mod instance;
+pub mod mipmap;
+pub mod model;
+pub mod msaa;
+pub mod pbr;
+pub mod post;
+pub mod preprocess;
+pub mod scene;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shader_check;
+pub mod shadow;
+pub mod texture;
+pub mod uniform;
+pub mod vertex;
 
 use winit::window::Window;
 
+/// 派生宏生成的代码通过这里引用依赖，不属于公开的 API
+#[doc(hidden)]
+pub mod __private {
+    pub use wgpu;
+
+    pub use crate::vertex::{Field, VertexField, attribute_count, attributes};
+}
+
 pub fn init_logger() {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+
+/// 逐级渲染生成 mip 链的管线，着色器、绑定组布局和采样器只创建一次，
+/// 渲染管线按纹理格式缓存
+///
+/// 每一级都以上一级为源，用线性过滤采样后绘制到当前级别，相当于 2x2 的盒式滤波。
+/// 由应用创建并持有，通过 [`crate::texture::TextureOptions::mipmapped`] 传给纹理加载函数。
+#[derive(Debug)]
+pub struct MipmapGenerator {
+    shader: wgpu::ShaderModule,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    sampler: wgpu::Sampler,
+    pipelines: Mutex<HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>>,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("blit.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Mipmap Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Mipmap Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Mipmap Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            shader,
+            bind_group_layout,
+            pipeline_layout,
+            sampler,
+            pipelines: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 第一次遇到 `format` 时创建对应的渲染管线
+    fn pipeline(&self, device: &wgpu::Device, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        let mut pipelines = self.pipelines.lock();
+        let pipeline = pipelines.entry(format).or_insert_with(|| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Mipmap Pipeline"),
+                layout: Some(&self.pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &self.shader,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        });
+        pipeline.clone()
+    }
+
+    /// 生成 `texture` 的完整 mip 链
+    ///
+    /// 纹理需要带有 `TEXTURE_BINDING | RENDER_ATTACHMENT` 用途，且格式可以作为渲染目标。
+    /// 数组纹理和立方体贴图的每一层分别生成。
+    pub fn generate(&self, device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+        let mip_level_count = texture.mip_level_count();
+        if mip_level_count <= 1 {
+            return;
+        }
+
+        // 每一层的每一级单独创建视图，views[layer][mip]
+        let views = (0..texture.depth_or_array_layers())
+            .map(|layer| {
+                (0..mip_level_count)
+                    .map(|mip| {
+                        texture.create_view(&wgpu::TextureViewDescriptor {
+                            label: Some("Mip View"),
+                            dimension: Some(wgpu::TextureViewDimension::D2),
+                            base_mip_level: mip,
+                            mip_level_count: Some(1),
+                            base_array_layer: layer,
+                            array_layer_count: Some(1),
+                            ..Default::default()
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let pipeline = self.pipeline(device, texture.format());
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+
+        for views in &views {
+            for target_mip in 1..mip_level_count as usize {
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Mipmap Bind Group"),
+                    layout: &self.bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&views[target_mip - 1]),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                });
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Mipmap Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &views[target_mip],
+                        depth_slice: None,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    ..Default::default()
+                });
+
+                render_pass.set_pipeline(&pipeline);
+                render_pass.set_bind_group(0, &bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{read_texture, test_context};
+
+    #[test]
+    fn each_level_is_the_box_filtered_previous_level() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+
+        // 线性格式，避免 sRGB 转换影响平均值；读回需要 `COPY_SRC`，所以不用 `Texture::from_rgba`
+        let size = 8;
+        let rgba: Vec<u8> = (0..size * size)
+            .flat_map(|i| {
+                let (x, y) = (i % size, i / size);
+                [(x * 32) as u8, (y * 32) as u8, ((x ^ y) * 16) as u8, 255]
+            })
+            .collect();
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        };
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: extent,
+            mip_level_count: extent.max_mips(wgpu::TextureDimension::D2),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        ctx.queue.write_texture(
+            texture.as_image_copy(),
+            &rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size),
+                rows_per_image: Some(size),
+            },
+            extent,
+        );
+        MipmapGenerator::new(&ctx.device).generate(&ctx.device, &ctx.queue, &texture);
+        assert_eq!(texture.mip_level_count(), 4);
+
+        let levels: Vec<_> = (0..4)
+            .map(|mip| {
+                pollster::block_on(read_texture(&ctx.device, &ctx.queue, &texture, mip)).unwrap()
+            })
+            .collect();
+
+        for mip in 1..4 {
+            let (source, level) = (&levels[mip - 1], &levels[mip]);
+            assert_eq!(level.width, source.width / 2);
+            for y in 0..level.height {
+                for x in 0..level.width {
+                    for channel in 0..4 {
+                        let texel = |x: u32, y: u32| {
+                            source.pixels[((y * source.width + x) * 4 + channel) as usize] as f32
+                        };
+                        let average = (texel(2 * x, 2 * y)
+                            + texel(2 * x + 1, 2 * y)
+                            + texel(2 * x, 2 * y + 1)
+                            + texel(2 * x + 1, 2 * y + 1))
+                            / 4.0;
+                        let actual = level.pixels[((y * level.width + x) * 4 + channel) as usize];
+                        assert!(
+                            (actual as f32 - average).abs() <= 1.0,
+                            "mip {mip} ({x}, {y}) channel {channel}: {actual} != {average}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pipelines_are_cached_per_format() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+
+        let generator = MipmapGenerator::new(&ctx.device);
+        for format in [
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba8Unorm,
+        ] {
+            let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: 4,
+                    height: 4,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 3,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            generator.generate(&ctx.device, &ctx.queue, &texture);
+        }
+        assert_eq!(generator.pipelines.lock().len(), 2);
+    }
+}
@@ -0,0 +1,835 @@
+use std::{fmt, ops::Range, path::Path};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    mipmap::MipmapGenerator,
+    texture::{Texture, TextureError, TextureOptions},
+    vertex::VertexLayout,
+};
+
+#[derive(Debug)]
+pub enum ModelError {
+    Obj(tobj::LoadError),
+    Texture(TextureError),
+    /// 网格引用的材质下标超出了 MTL 文件中的材质数量
+    MissingMaterial {
+        mesh: String,
+        material: usize,
+        count: usize,
+    },
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Obj(e) => write!(f, "failed to load obj: {e}"),
+            ModelError::Texture(e) => write!(f, "failed to load material texture: {e}"),
+            ModelError::MissingMaterial {
+                mesh,
+                material,
+                count,
+            } => write!(
+                f,
+                "mesh {mesh:?} uses material {material} but only {count} materials were loaded"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+impl From<tobj::LoadError> for ModelError {
+    fn from(e: tobj::LoadError) -> Self {
+        ModelError::Obj(e)
+    }
+}
+
+impl From<TextureError> for ModelError {
+    fn from(e: TextureError) -> Self {
+        ModelError::Texture(e)
+    }
+}
+
+/// 模型顶点：位置、纹理坐标、法线、切线，分别对应 `@location(0)` 到 `@location(3)`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable, VertexLayout)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    /// 与 glTF 相同，`w` 为副切线的方向，副切线为 `cross(normal, tangent.xyz) * w`
+    pub tangent: [f32; 4],
+}
+
+impl ModelVertex {
+    /// 没有纹理坐标时使用的切线
+    pub const DEFAULT_TANGENT: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+}
+
+/// 材质：漫反射纹理和对应的绑定组，绑定组布局见 [`Material::bind_group_layout`]
+pub struct Material {
+    pub name: String,
+    pub diffuse_texture: Texture,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Material {
+    /// `@binding(0)` 为漫反射纹理，`@binding(1)` 为采样器
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Material Bind Group Layout"),
+            entries: &Self::bind_group_layout_entries(),
+        })
+    }
+
+    pub fn bind_group_layout_entries() -> [wgpu::BindGroupLayoutEntry; 2] {
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        diffuse_texture: Texture,
+        layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(name),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                },
+            ],
+        });
+
+        Self {
+            name: name.to_string(),
+            diffuse_texture,
+            bind_group,
+        }
+    }
+}
+
+pub struct Mesh {
+    pub name: String,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_elements: u32,
+    /// 在 [`Model::materials`] 中的下标
+    pub material: usize,
+}
+
+impl Mesh {
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        vertices: &[ModelVertex],
+        indices: &[u32],
+        material: usize,
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Vertex Buffer")),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Index Buffer")),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            name: name.to_string(),
+            vertex_buffer,
+            index_buffer,
+            num_elements: indices.len() as u32,
+            material,
+        }
+    }
+}
+
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+}
+
+impl Model {
+    /// 读取 OBJ 文件以及它引用的 MTL 文件，纹理路径相对于 OBJ 所在目录
+    ///
+    /// 没有漫反射贴图的材质使用 `Kd` 颜色生成 1x1 纹理；没有材质的网格会使用一个白色的默认材质
+    pub fn load_obj(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        path: impl AsRef<Path>,
+        layout: &wgpu::BindGroupLayout,
+    ) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        let parent = path.parent().unwrap_or(Path::new(""));
+
+        let (obj_models, obj_materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)?;
+        let obj_materials = obj_materials?;
+        check_material_ids(&obj_models, obj_materials.len())?;
+
+        let mut materials = Vec::with_capacity(obj_materials.len() + 1);
+        for m in &obj_materials {
+            let diffuse_texture = match &m.diffuse_texture {
+                Some(file) => Texture::from_path(
+                    device,
+                    queue,
+                    parent.join(file),
+                    TextureOptions::mipmapped(mipmaps),
+                )?,
+                None => {
+                    let [r, g, b] = m.diffuse.unwrap_or([1.0; 3]);
+                    solid_color_texture(device, queue, &m.name, [r, g, b, 1.0])?
+                }
+            };
+            materials.push(Material::new(device, &m.name, diffuse_texture, layout));
+        }
+
+        let default_material = materials.len();
+        if obj_models.iter().any(|m| m.mesh.material_id.is_none()) {
+            let texture = solid_color_texture(device, queue, "Default Material", [1.0; 4])?;
+            materials.push(Material::new(device, "Default Material", texture, layout));
+        }
+
+        let meshes = obj_models
+            .iter()
+            .map(|m| {
+                let mut vertices = mesh_vertices(&m.mesh);
+                let mut indices = m.mesh.indices.clone();
+                if m.mesh.normals.is_empty() {
+                    compute_normals(&mut vertices, &indices);
+                }
+                if !m.mesh.texcoords.is_empty() {
+                    compute_tangents(&mut vertices, &mut indices);
+                }
+                Mesh::new(
+                    device,
+                    &m.name,
+                    &vertices,
+                    &indices,
+                    m.mesh.material_id.unwrap_or(default_material),
+                )
+            })
+            .collect();
+
+        Ok(Self { meshes, materials })
+    }
+}
+
+/// 检查每个网格的材质下标都指向已加载的材质，避免绘制时越界
+fn check_material_ids(models: &[tobj::Model], count: usize) -> Result<(), ModelError> {
+    for model in models {
+        if let Some(material) = model.mesh.material_id
+            && material >= count
+        {
+            return Err(ModelError::MissingMaterial {
+                mesh: model.name.clone(),
+                material,
+                count,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 把 tobj 的分离数组合并成交错的顶点；缺少的纹理坐标和法线先用 0 填充，切线使用默认值
+fn mesh_vertices(mesh: &tobj::Mesh) -> Vec<ModelVertex> {
+    (0..mesh.positions.len() / 3)
+        .map(|i| ModelVertex {
+            position: [
+                mesh.positions[i * 3],
+                mesh.positions[i * 3 + 1],
+                mesh.positions[i * 3 + 2],
+            ],
+            // OBJ 的 v 轴向上，wgpu 的纹理坐标 v 轴向下
+            tex_coords: match mesh.texcoords.get(i * 2..i * 2 + 2) {
+                Some(&[u, v]) => [u, 1.0 - v],
+                _ => [0.0; 2],
+            },
+            normal: match mesh.normals.get(i * 3..i * 3 + 3) {
+                Some(&[x, y, z]) => [x, y, z],
+                _ => [0.0; 3],
+            },
+            tangent: ModelVertex::DEFAULT_TANGENT,
+        })
+        .collect()
+}
+
+/// 按面积加权平均相邻三角形的法线，用于补全没有法线的网格
+pub fn compute_normals(vertices: &mut [ModelVertex], indices: &[u32]) {
+    let mut normals = vec![glam::Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] =
+            [0, 1, 2].map(|i| glam::Vec3::from(vertices[triangle[i] as usize].position));
+        // 叉积的长度是三角形面积的两倍，不归一化就是按面积加权
+        let normal = (b - a).cross(c - a);
+        for &i in triangle {
+            normals[i as usize] += normal;
+        }
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        vertex.normal = normal.normalize_or_zero().to_array();
+    }
+}
+
+/// 用 MikkTSpace 算法生成切线，结果与 Blender 等工具烘焙法线贴图时使用的切线空间一致
+///
+/// 切线空间的 Y 轴指向纹理图片的上方，即纹理坐标 v 减小的方向，与 glTF 的约定相同。
+/// MikkTSpace 按面的角生成切线，同一个顶点在不同的面上可能得到不同的切线（例如纹理坐标镜像的接缝），
+/// 因此先展开索引逐角生成，再把切线相同的角合并回原来的顶点，切线不同的角复制出新的顶点并改写
+/// `indices`。生成失败时（例如没有三角形）保留原来的切线
+pub fn compute_tangents(vertices: &mut Vec<ModelVertex>, indices: &mut [u32]) {
+    struct Corners(Vec<ModelVertex>);
+
+    impl bevy_mikktspace::Geometry for Corners {
+        fn num_faces(&self) -> usize {
+            self.0.len() / 3
+        }
+
+        fn num_vertices_of_face(&self, _face: usize) -> usize {
+            3
+        }
+
+        fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+            self.0[face * 3 + vert].position
+        }
+
+        fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+            self.0[face * 3 + vert].normal
+        }
+
+        fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+            // MikkTSpace 的副切线指向 v 增大的方向，翻转 v 使它指向图片上方
+            let [u, v] = self.0[face * 3 + vert].tex_coords;
+            [u, 1.0 - v]
+        }
+
+        fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+            self.0[face * 3 + vert].tangent = tangent;
+        }
+    }
+
+    let triangles = indices.len() / 3 * 3;
+    let mut corners = Corners(
+        indices[..triangles]
+            .iter()
+            .map(|&i| vertices[i as usize])
+            .collect(),
+    );
+    if !bevy_mikktspace::generate_tangents(&mut corners) {
+        log::warn!("Failed to generate tangents");
+        return;
+    }
+
+    // 每个原始顶点已经使用的切线和对应的顶点下标
+    let mut welded: Vec<Vec<([f32; 4], u32)>> = vec![Vec::new(); vertices.len()];
+    for (index, corner) in indices.iter_mut().zip(corners.0) {
+        let variants = &mut welded[*index as usize];
+        if let Some(&(_, existing)) = variants.iter().find(|(t, _)| *t == corner.tangent) {
+            *index = existing;
+        } else if variants.is_empty() {
+            vertices[*index as usize].tangent = corner.tangent;
+            variants.push((corner.tangent, *index));
+        } else {
+            let split = vertices.len() as u32;
+            vertices.push(corner);
+            variants.push((corner.tangent, split));
+            *index = split;
+        }
+    }
+}
+
+/// 用单一颜色生成 1x1 纹理，各分量的范围为 `[0, 1]`
+pub(crate) fn solid_color_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &str,
+    color: [f32; 4],
+) -> Result<Texture, TextureError> {
+    let rgba = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Texture::from_rgba(
+        device,
+        queue,
+        &rgba,
+        1,
+        1,
+        Some(label),
+        TextureOptions::default(),
+    )
+}
+
+/// [`DrawModel`] 在材质之外设置的绑定
+#[derive(Clone, Copy, Default)]
+pub struct ModelBindings<'a> {
+    /// 依次设置到 `@group(1)` 及之后的绑定组和它们的动态偏移，例如相机和节点变换
+    pub bind_groups: &'a [(&'a wgpu::BindGroup, &'a [wgpu::DynamicOffset])],
+    /// 设置到顶点缓冲槽 1 的实例缓冲，例如 [`crate::instance::InstanceBuffer::slice`]
+    pub instances: Option<wgpu::BufferSlice<'a>>,
+}
+
+impl ModelBindings<'_> {
+    fn set(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        for (index, (bind_group, offsets)) in self.bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(index as u32 + 1, *bind_group, offsets);
+        }
+        if let Some(instances) = self.instances {
+            render_pass.set_vertex_buffer(1, instances);
+        }
+    }
+}
+
+/// 在渲染通道上绘制 [`Mesh`] 和 [`Model`]
+///
+/// 约定材质位于 `@group(0)`，网格的顶点位于顶点缓冲槽 0，其余绑定由 [`ModelBindings`] 提供
+pub trait DrawModel<'a> {
+    /// `material` 可以是 [`Material`] 或 PBR 材质的绑定组
+    fn draw_mesh(
+        &mut self,
+        mesh: &'a Mesh,
+        material: &'a wgpu::BindGroup,
+        bindings: &ModelBindings<'_>,
+    );
+    fn draw_mesh_instanced(
+        &mut self,
+        mesh: &'a Mesh,
+        material: &'a wgpu::BindGroup,
+        instances: Range<u32>,
+        bindings: &ModelBindings<'_>,
+    );
+
+    fn draw_model(&mut self, model: &'a Model, bindings: &ModelBindings<'_>);
+    fn draw_model_instanced(
+        &mut self,
+        model: &'a Model,
+        instances: Range<u32>,
+        bindings: &ModelBindings<'_>,
+    );
+}
+
+impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
+where
+    'b: 'a,
+{
+    fn draw_mesh(
+        &mut self,
+        mesh: &'b Mesh,
+        material: &'b wgpu::BindGroup,
+        bindings: &ModelBindings<'_>,
+    ) {
+        self.draw_mesh_instanced(mesh, material, 0..1, bindings);
+    }
+
+    fn draw_mesh_instanced(
+        &mut self,
+        mesh: &'b Mesh,
+        material: &'b wgpu::BindGroup,
+        instances: Range<u32>,
+        bindings: &ModelBindings<'_>,
+    ) {
+        bindings.set(self);
+        self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        self.set_bind_group(0, material, &[]);
+        self.draw_indexed(0..mesh.num_elements, 0, instances);
+    }
+
+    fn draw_model(&mut self, model: &'b Model, bindings: &ModelBindings<'_>) {
+        self.draw_model_instanced(model, 0..1, bindings);
+    }
+
+    fn draw_model_instanced(
+        &mut self,
+        model: &'b Model,
+        instances: Range<u32>,
+        bindings: &ModelBindings<'_>,
+    ) {
+        bindings.set(self);
+        for mesh in &model.meshes {
+            let material = &model.materials[mesh.material].bind_group;
+            self.draw_mesh_instanced(mesh, material, instances.clone(), &ModelBindings::default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        headless::{HeadlessContext, OffscreenTarget, TempDir, require_adapter, test_context},
+        instance::{Instance, InstanceBuffer, InstanceRaw},
+        uniform::UniformBuffer,
+    };
+
+    const QUAD_OBJ: &str = "\
+mtllib quad.mtl
+o Plain
+v 0 0 1
+v 1 0 1
+v 1 1 1
+f 1 2 3
+o Textured
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl tree
+f 4/1/1 5/2/1 6/3/1 7/4/1
+";
+
+    const QUAD_MTL: &str = "\
+newmtl tree
+Kd 1 1 1
+map_Kd tree.png
+";
+
+    /// 在临时目录中写出测试用的 OBJ、MTL 和纹理
+    fn write_assets(name: &str) -> TempDir {
+        let dir = TempDir::new(&format!("utils-model-{name}"));
+        std::fs::write(dir.join("quad.obj"), QUAD_OBJ).unwrap();
+        std::fs::write(dir.join("quad.mtl"), QUAD_MTL).unwrap();
+        image::RgbaImage::from_pixel(4, 4, image::Rgba([0, 255, 0, 255]))
+            .save(dir.join("tree.png"))
+            .unwrap();
+        dir
+    }
+
+    #[test]
+    fn obj_meshes_are_triangulated_and_flipped() {
+        let dir = write_assets("cpu");
+        let (models, materials) =
+            tobj::load_obj(dir.join("quad.obj"), &tobj::GPU_LOAD_OPTIONS).unwrap();
+
+        assert_eq!(materials.unwrap().len(), 1);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].mesh.material_id, None);
+        assert_eq!(models[1].mesh.indices.len(), 6);
+
+        let vertices = mesh_vertices(&models[1].mesh);
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(vertices[0].tex_coords, [0.0, 1.0]);
+        assert_eq!(vertices[2].normal, [0.0, 0.0, 1.0]);
+
+        let vertices = mesh_vertices(&models[0].mesh);
+        assert_eq!(vertices[0].tex_coords, [0.0, 0.0]);
+        assert_eq!(vertices[0].normal, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn computed_normals_are_area_weighted() {
+        let vertex = |x: f32, y: f32, z: f32| ModelVertex {
+            position: [x, y, z],
+            tex_coords: [0.0; 2],
+            normal: [0.0; 3],
+            tangent: ModelVertex::DEFAULT_TANGENT,
+        };
+        // 共享一条边的两个三角形：大的朝 +Z，小的朝 +X
+        let mut vertices = [
+            vertex(0.0, 0.0, 0.0),
+            vertex(0.0, 1.0, 0.0),
+            vertex(-2.0, 0.0, 0.0),
+            vertex(0.0, 0.0, -0.5),
+        ];
+        compute_normals(&mut vertices, &[0, 1, 2, 0, 3, 1]);
+
+        assert_eq!(vertices[2].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(vertices[3].normal, [1.0, 0.0, 0.0]);
+        let shared = glam::Vec3::from(vertices[0].normal);
+        assert!(shared.abs_diff_eq(glam::Vec3::new(0.5, 0.0, 2.0).normalize(), 1e-6));
+    }
+
+    #[test]
+    fn tangents_follow_texture_axes() {
+        let vertex = |x: f32, y: f32, u: f32, v: f32| ModelVertex {
+            position: [x, y, 0.0],
+            tex_coords: [u, v],
+            normal: [0.0, 0.0, 1.0],
+            tangent: [0.0; 4],
+        };
+        // 纹理坐标 v 轴向下，图片的上方为 +Y
+        let mut vertices = vec![
+            vertex(0.0, 0.0, 0.0, 1.0),
+            vertex(1.0, 0.0, 1.0, 1.0),
+            vertex(1.0, 1.0, 1.0, 0.0),
+            vertex(0.0, 1.0, 0.0, 0.0),
+        ];
+        let mut indices = [0, 1, 2, 0, 2, 3];
+        compute_tangents(&mut vertices, &mut indices);
+
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+        for vertex in &vertices {
+            let tangent = glam::Vec4::from(vertex.tangent);
+            assert!(tangent.abs_diff_eq(glam::Vec4::new(1.0, 0.0, 0.0, 1.0), 1e-5));
+            let bitangent = glam::Vec3::from(vertex.normal).cross(tangent.truncate()) * tangent.w;
+            assert!(bitangent.abs_diff_eq(glam::Vec3::Y, 1e-5));
+        }
+
+        // 水平镜像的纹理坐标使副切线方向翻转
+        for vertex in &mut vertices {
+            vertex.tex_coords[0] = 1.0 - vertex.tex_coords[0];
+        }
+        compute_tangents(&mut vertices, &mut indices);
+        assert!(
+            glam::Vec4::from(vertices[0].tangent)
+                .abs_diff_eq(glam::Vec4::new(-1.0, 0.0, 0.0, -1.0), 1e-5)
+        );
+    }
+
+    #[test]
+    fn mirrored_uv_seam_splits_shared_vertices() {
+        let vertex = |x: f32, y: f32| ModelVertex {
+            position: [x, y, 0.0],
+            // 以 x = 0 为轴镜像的纹理坐标，两侧共用中间的两个顶点
+            tex_coords: [x.abs(), 1.0 - y],
+            normal: [0.0, 0.0, 1.0],
+            tangent: [0.0; 4],
+        };
+        let mut vertices = vec![
+            vertex(-1.0, 0.0),
+            vertex(0.0, 0.0),
+            vertex(1.0, 0.0),
+            vertex(-1.0, 1.0),
+            vertex(0.0, 1.0),
+            vertex(1.0, 1.0),
+        ];
+        // 左半边和右半边各两个三角形
+        let mut indices = [0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4];
+        compute_tangents(&mut vertices, &mut indices);
+
+        // 中间的两个顶点在两侧的切线不同，各复制出一个顶点
+        assert_eq!(vertices.len(), 8);
+        for (triangle, expected) in indices.chunks_exact(3).zip([-1.0, -1.0, 1.0, 1.0]) {
+            for &index in triangle {
+                let tangent = glam::Vec4::from(vertices[index as usize].tangent);
+                let expected = glam::Vec4::new(expected, 0.0, 0.0, expected);
+                assert!(
+                    tangent.abs_diff_eq(expected, 1e-5),
+                    "vertex {index}: {tangent}"
+                );
+            }
+        }
+        // 复制出的顶点保留原来的其他属性
+        for &index in &indices {
+            assert_eq!(
+                vertices[index as usize].tex_coords[0],
+                vertices[index as usize].position[0].abs()
+            );
+        }
+    }
+
+    #[test]
+    fn load_obj_creates_default_material() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+        let dir = write_assets("gpu");
+        let layout = Material::bind_group_layout(&ctx.device);
+
+        let model = Model::load_obj(
+            &ctx.device,
+            &ctx.queue,
+            &MipmapGenerator::new(&ctx.device),
+            dir.join("quad.obj"),
+            &layout,
+        )
+        .unwrap();
+
+        assert_eq!(model.meshes.len(), 2);
+        assert_eq!(model.materials.len(), 2);
+        assert_eq!(model.meshes[0].material, 1);
+        assert_eq!(model.meshes[0].num_elements, 3);
+        assert_eq!(model.meshes[1].material, 0);
+        assert_eq!(model.materials[0].diffuse_texture.size.width, 4);
+    }
+
+    #[test]
+    fn out_of_range_material_id_is_rejected() {
+        let mesh = tobj::Mesh {
+            material_id: Some(1),
+            ..Default::default()
+        };
+        let models = [tobj::Model::new(mesh, "Quad".to_string())];
+
+        assert!(check_material_ids(&models, 2).is_ok());
+        assert!(matches!(
+            check_material_ids(&models, 1),
+            Err(ModelError::MissingMaterial {
+                material: 1,
+                count: 1,
+                ..
+            })
+        ));
+    }
+
+    /// 把网格的顶点按实例变换，再乘以 `@group(1)` 的矩阵，输出纹理颜色
+    const DRAW_MODEL_WGSL: &str = "
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct InstanceInput {
+    @location(5) model_0: vec4<f32>,
+    @location(6) model_1: vec4<f32>,
+    @location(7) model_2: vec4<f32>,
+    @location(8) model_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@group(0) @binding(0) var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1) var s_diffuse: sampler;
+@group(1) @binding(0) var<uniform> view_proj: mat4x4<f32>;
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    var out: VertexOutput;
+    out.clip_position = view_proj * model * vec4<f32>(vertex.position, 1.0);
+    out.tex_coords = vertex.tex_coords;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+}
+";
+
+    #[test]
+    fn draw_model_sets_camera_and_instances() {
+        let Some(ctx) = require_adapter(
+            pollster::block_on(HeadlessContext::new(winit::dpi::PhysicalSize::new(4, 4))),
+            "draw model test",
+        ) else {
+            return;
+        };
+        let dir = write_assets("draw");
+        let device = &ctx.device;
+        let layout = Material::bind_group_layout(device);
+        let model = Model::load_obj(
+            device,
+            &ctx.queue,
+            &MipmapGenerator::new(device),
+            dir.join("quad.obj"),
+            &layout,
+        )
+        .unwrap();
+
+        // 相机把 [0, 1] 的网格移到裁剪空间的左下角，实例把 z 压缩到 [0, 0.5]
+        let camera = UniformBuffer::new(
+            device,
+            "Camera",
+            wgpu::ShaderStages::VERTEX,
+            glam::Mat4::from_translation(glam::Vec3::new(-1.0, -1.0, 0.0)).to_cols_array_2d(),
+        );
+        let instances = InstanceBuffer::new(
+            device,
+            &[Instance {
+                scale: glam::Vec3::new(1.0, 1.0, 0.5),
+                ..Default::default()
+            }],
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Draw Model Shader"),
+            source: wgpu::ShaderSource::Wgsl(DRAW_MODEL_WGSL.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout, camera.layout()],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Draw Model Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[ModelVertex::LAYOUT, InstanceRaw::LAYOUT],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(ctx.config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let target = OffscreenTarget::new(device, &ctx.config);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            render_pass.set_pipeline(&pipeline);
+            render_pass.draw_model(
+                &model,
+                &ModelBindings {
+                    bind_groups: &[(camera.bind_group(), &[])],
+                    instances: Some(instances.slice()),
+                },
+            );
+        }
+        ctx.queue.submit([encoder.finish()]);
+
+        let frame = pollster::block_on(target.read_pixels(device, &ctx.queue)).unwrap();
+        let image = frame.to_image();
+        // 后绘制的绿色四边形覆盖了左下四分之一中的白色三角形，其余部分保持清除颜色
+        assert_eq!(image.get_pixel(0, 3).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(1, 2).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(3, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(2, 3).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,188 @@
+/// 尝试的采样数，1 表示不使用多重采样
+pub const SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+
+/// 设备能在所有 `formats` 上使用的采样数，从小到大排列，至少包含 1
+///
+/// 颜色格式还要求支持解析；设备没有开启 `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` 时
+/// 只能使用 WebGPU 保证的采样数
+pub fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    formats: &[wgpu::TextureFormat],
+) -> Vec<u32> {
+    let device_features = device.features();
+    let features: Vec<_> = formats
+        .iter()
+        .map(|format| {
+            if device_features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+                adapter.get_texture_format_features(*format)
+            } else {
+                format.guaranteed_format_features(device_features)
+            }
+        })
+        .collect();
+    let is_color: Vec<_> = formats
+        .iter()
+        .map(|format| !format.is_depth_stencil_format())
+        .collect();
+    common_sample_counts(&features, &is_color)
+}
+
+fn common_sample_counts(features: &[wgpu::TextureFormatFeatures], is_color: &[bool]) -> Vec<u32> {
+    SAMPLE_COUNTS
+        .into_iter()
+        .filter(|&count| {
+            count == 1
+                || features.iter().zip(is_color).all(|(features, &is_color)| {
+                    features.flags.sample_count_supported(count)
+                        && (!is_color
+                            || features
+                                .flags
+                                .contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE))
+                })
+        })
+        .collect()
+}
+
+/// 多重采样的颜色目标，渲染通道结束时解析到单采样的纹理
+///
+/// 采样数为 1 时不创建额外的纹理，直接渲染到目标纹理
+pub struct MsaaTarget {
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    view: Option<wgpu::TextureView>,
+}
+
+impl MsaaTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        Self {
+            format,
+            sample_count,
+            view: Self::create_view(device, config, format, sample_count),
+        }
+    }
+
+    fn create_view(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Option<wgpu::TextureView> {
+        if sample_count <= 1 {
+            return None;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Color Texture"),
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// 管线的多重采样状态需要与目标一致
+    pub fn multisample_state(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.view = Self::create_view(device, config, self.format, self.sample_count);
+    }
+
+    pub fn set_sample_count(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) {
+        self.sample_count = sample_count;
+        self.resize(device, config);
+    }
+
+    /// 渲染到 `target` 的颜色附件；多重采样时渲染到内部纹理再解析到 `target`，
+    /// 多重采样的结果解析后不再需要，不必写回
+    pub fn color_attachment<'a>(
+        &'a self,
+        target: &'a wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        match &self.view {
+            Some(view) => wgpu::RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: Some(target),
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Discard,
+                },
+            },
+            None => wgpu::RenderPassColorAttachment {
+                view: target,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(flags: wgpu::TextureFormatFeatureFlags) -> wgpu::TextureFormatFeatures {
+        wgpu::TextureFormatFeatures {
+            allowed_usages: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            flags,
+        }
+    }
+
+    #[test]
+    fn sample_counts_are_intersected_across_formats() {
+        use wgpu::TextureFormatFeatureFlags as Flags;
+
+        let color =
+            features(Flags::MULTISAMPLE_X2 | Flags::MULTISAMPLE_X4 | Flags::MULTISAMPLE_RESOLVE);
+        let depth = features(Flags::MULTISAMPLE_X4 | Flags::MULTISAMPLE_X8);
+
+        assert_eq!(
+            common_sample_counts(&[color, depth], &[true, false]),
+            [1, 4]
+        );
+    }
+
+    #[test]
+    fn color_formats_must_support_resolve() {
+        use wgpu::TextureFormatFeatureFlags as Flags;
+
+        let color = features(Flags::MULTISAMPLE_X4);
+
+        assert_eq!(common_sample_counts(&[color], &[true]), [1]);
+        assert_eq!(common_sample_counts(&[color], &[false]), [1, 4]);
+    }
+}
@@ -0,0 +1,220 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::texture::{Texture, TextureError, TextureOptions};
+
+/// 与着色器中的 `Material` 对应的金属度-粗糙度参数，每张贴图的采样结果都会乘以对应的系数
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct PbrFactors {
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    /// 法线贴图 XY 分量的缩放
+    pub normal_scale: f32,
+    /// 环境光遮蔽的强度，0 表示不遮蔽
+    pub occlusion_strength: f32,
+    /// 透明度低于该值的片元被丢弃，0 表示不做透明度测试
+    pub alpha_cutoff: f32,
+}
+
+impl Default for PbrFactors {
+    /// glTF 规定的默认值，不做透明度测试
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            emissive: [0.0; 3],
+            metallic: 1.0,
+            roughness: 1.0,
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            alpha_cutoff: 0.0,
+        }
+    }
+}
+
+/// PBR 材质使用的贴图，缺少的贴图用不影响结果的 1x1 纹理代替
+#[derive(Default)]
+pub struct PbrMaps {
+    /// sRGB
+    pub base_color: Option<Texture>,
+    /// 线性，G 通道为粗糙度，B 通道为金属度
+    pub metallic_roughness: Option<Texture>,
+    /// 线性，切线空间法线
+    pub normal: Option<Texture>,
+    /// 线性，R 通道为环境光遮蔽
+    pub occlusion: Option<Texture>,
+    /// sRGB
+    pub emissive: Option<Texture>,
+}
+
+/// 金属度-粗糙度材质的参数、贴图和绑定组
+///
+/// 绑定组布局见 [`PbrMaterial::bind_group_layout`]
+pub struct PbrMaterial {
+    pub name: String,
+    pub factors: PbrFactors,
+    factors_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl PbrMaterial {
+    /// `@binding(0)` 为 [`PbrFactors`]，`@binding(1)` 到 `@binding(5)` 依次为基础颜色、
+    /// 金属度-粗糙度、法线、环境光遮蔽、自发光贴图，`@binding(6)` 为采样器
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("PBR Material Bind Group Layout"),
+            entries: &Self::bind_group_layout_entries(),
+        })
+    }
+
+    pub fn bind_group_layout_entries() -> [wgpu::BindGroupLayoutEntry; 7] {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            texture(1),
+            texture(2),
+            texture(3),
+            texture(4),
+            texture(5),
+            wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        name: &str,
+        factors: PbrFactors,
+        maps: PbrMaps,
+    ) -> Result<Self, TextureError> {
+        let fallback = |texture: Option<Texture>, rgba: [u8; 4], linear: bool| match texture {
+            Some(texture) => Ok(texture),
+            None => Texture::from_rgba(
+                device,
+                queue,
+                &rgba,
+                1,
+                1,
+                Some(name),
+                TextureOptions {
+                    linear,
+                    ..Default::default()
+                },
+            ),
+        };
+
+        let base_color = fallback(maps.base_color, [255; 4], false)?;
+        let metallic_roughness = fallback(maps.metallic_roughness, [255; 4], true)?;
+        let normal = fallback(maps.normal, [128, 128, 255, 255], true)?;
+        let occlusion = fallback(maps.occlusion, [255; 4], true)?;
+        let emissive = fallback(maps.emissive, [255; 4], false)?;
+
+        let factors_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Factors Buffer")),
+            contents: bytemuck::cast_slice(&[factors]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("PBR Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(name),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: factors_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&base_color.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&metallic_roughness.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&normal.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&occlusion.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&emissive.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Ok(Self {
+            name: name.to_string(),
+            factors,
+            factors_buffer,
+            bind_group,
+        })
+    }
+
+    /// 修改 `factors` 后调用，把参数写入 GPU
+    pub fn update(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.factors_buffer,
+            0,
+            bytemuck::cast_slice(&[self.factors]),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factors_layout_matches_wgsl() {
+        assert_eq!(std::mem::offset_of!(PbrFactors, emissive), 16);
+        assert_eq!(std::mem::offset_of!(PbrFactors, metallic), 28);
+        assert_eq!(std::mem::offset_of!(PbrFactors, occlusion_strength), 40);
+        assert_eq!(std::mem::size_of::<PbrFactors>(), 48);
+    }
+}
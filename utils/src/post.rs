@@ -0,0 +1,350 @@
+//! 后处理链：每个效果读取上一个效果的输出，在两张同样大小的纹理之间来回渲染，
+//! 最后一个效果直接写入表面
+
+mod bloom;
+mod fxaa;
+mod gamma;
+mod grading;
+mod vignette;
+
+use std::any::Any;
+
+use wgpu::util::DeviceExt;
+
+pub use bloom::{Bloom, BloomParams};
+pub use fxaa::Fxaa;
+pub use gamma::Gamma;
+pub use grading::{ColorGrading, ColorLut};
+pub use vignette::{Vignette, VignetteParams};
+
+use crate::{preprocess::Preprocessor, texture::Texture};
+
+/// 一个全屏的后处理效果
+pub trait PostEffect: Any {
+    fn name(&self) -> &'static str;
+
+    /// 窗口尺寸变化时调用，用于重新创建与尺寸相关的资源
+    fn resize(&mut self, _device: &wgpu::Device, _config: &wgpu::SurfaceConfiguration) {}
+
+    /// 读取 `input`，把结果写入 `output`
+    fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        output: &wgpu::TextureView,
+    );
+}
+
+struct Stage {
+    effect: Box<dyn PostEffect>,
+    enabled: bool,
+}
+
+/// 按顺序执行的后处理效果列表
+///
+/// 场景先渲染到 [`PostChain::input_view`]，[`PostChain::render`] 再依次执行启用的效果；
+/// 没有启用任何效果时直接把输入复制到输出
+pub struct PostChain {
+    format: wgpu::TextureFormat,
+    /// 乒乓纹理，第 `i` 个效果读取 `targets[i % 2]`，写入另一张
+    targets: [Texture; 2],
+    copy: FullscreenPass,
+    stages: Vec<Stage>,
+}
+
+impl PostChain {
+    /// 中间纹理使用表面的格式，因此场景和各个效果的管线都以 `config.format` 为目标
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let shader = create_shader(
+            device,
+            "Post Copy Shader",
+            "copy.wgsl",
+            include_str!("post/copy.wgsl"),
+        );
+        let copy = FullscreenPass::new(device, "Post Copy", &shader, "fs_main", config.format, &[]);
+
+        Self {
+            format: config.format,
+            targets: Self::create_targets(device, config),
+            copy,
+            stages: Vec::new(),
+        }
+    }
+
+    fn create_targets(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> [Texture; 2] {
+        [
+            Texture::create_render_target(device, config, config.format, Some("Post Target A")),
+            Texture::create_render_target(device, config, config.format, Some("Post Target B")),
+        ]
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// 在链的末尾添加一个启用的效果
+    pub fn push(&mut self, effect: impl PostEffect) {
+        self.stages.push(Stage {
+            effect: Box::new(effect),
+            enabled: true,
+        });
+    }
+
+    pub fn with(mut self, effect: impl PostEffect) -> Self {
+        self.push(effect);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    pub fn name(&self, index: usize) -> Option<&'static str> {
+        self.stages.get(index).map(|stage| stage.effect.name())
+    }
+
+    pub fn is_enabled(&self, index: usize) -> bool {
+        self.stages.get(index).is_some_and(|stage| stage.enabled)
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(stage) = self.stages.get_mut(index) {
+            stage.enabled = enabled;
+        }
+    }
+
+    pub fn set_all_enabled(&mut self, enabled: bool) {
+        for stage in &mut self.stages {
+            stage.enabled = enabled;
+        }
+    }
+
+    /// 找到第一个类型为 `T` 的效果，用于修改它的参数
+    pub fn effect_mut<T: PostEffect>(&mut self) -> Option<&mut T> {
+        self.stages.iter_mut().find_map(|stage| {
+            let effect: &mut dyn Any = stage.effect.as_mut();
+            effect.downcast_mut::<T>()
+        })
+    }
+
+    /// 在 `set_window_size` 之后调用，重新创建乒乓纹理和各个效果的资源
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.targets = Self::create_targets(device, config);
+        for stage in &mut self.stages {
+            stage.effect.resize(device, config);
+        }
+    }
+
+    /// 场景渲染通道的颜色附件
+    pub fn input_view(&self) -> &wgpu::TextureView {
+        &self.targets[0].view
+    }
+
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::TextureView,
+    ) {
+        let enabled: Vec<&dyn PostEffect> = self
+            .stages
+            .iter()
+            .filter(|stage| stage.enabled)
+            .map(|stage| stage.effect.as_ref())
+            .collect();
+
+        if enabled.is_empty() {
+            self.copy
+                .render(device, encoder, &self.targets[0], &[], output);
+            return;
+        }
+
+        for (i, effect) in enabled.iter().enumerate() {
+            let input = &self.targets[i % 2];
+            let target = if i + 1 == enabled.len() {
+                output
+            } else {
+                &self.targets[(i + 1) % 2].view
+            };
+            effect.render(device, encoder, input, target);
+        }
+    }
+}
+
+/// 全屏三角形管线，`@group(0)` 的绑定 0 和 1 固定为输入纹理和它的采样器，
+/// 效果自己的参数从绑定 2 开始
+pub struct FullscreenPass {
+    label: String,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl FullscreenPass {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        shader: &wgpu::ShaderModule,
+        entry_point: &str,
+        format: wgpu::TextureFormat,
+        extra_entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> Self {
+        let mut entries = vec![
+            texture_entry(0),
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ];
+        entries.extend_from_slice(extra_entries);
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{label} Bind Group Layout")),
+            entries: &entries,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&format!("{label} Pipeline Layout")),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("{label} Pipeline")),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            label: label.to_string(),
+            pipeline,
+            bind_group_layout,
+        }
+    }
+
+    /// 输入纹理每帧都可能不同，绑定组在绘制时创建
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        extra_entries: &[wgpu::BindGroupEntry],
+        output: &wgpu::TextureView,
+    ) {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&input.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&input.sampler),
+            },
+        ];
+        entries.extend_from_slice(extra_entries);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Bind Group", self.label)),
+            layout: &self.bind_group_layout,
+            entries: &entries,
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&self.label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    }
+}
+
+fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_params_buffer<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    label: &str,
+    params: &T,
+) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::bytes_of(params),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+/// 创建效果的着色器，`source` 可以 `#include "fullscreen.wgsl"`
+fn create_shader(
+    device: &wgpu::Device,
+    label: &str,
+    name: &str,
+    source: &str,
+) -> wgpu::ShaderModule {
+    Preprocessor::new()
+        .with_file("fullscreen.wgsl", include_str!("post/fullscreen.wgsl"))
+        .with_file(name, source)
+        .create_shader_module(device, label, name)
+}
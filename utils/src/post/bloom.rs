@@ -0,0 +1,177 @@
+use bytemuck::{Pod, Zeroable};
+
+use super::{
+    FullscreenPass, PostEffect, create_params_buffer, create_shader, texture_entry, uniform_entry,
+};
+use crate::texture::Texture;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct BloomParams {
+    /// 亮度超过该值的部分才会泛光
+    pub threshold: f32,
+    /// 阈值两侧的柔和过渡宽度，为 0 时是硬阈值
+    pub knee: f32,
+    /// 叠加回原图时的强度
+    pub intensity: f32,
+    pub _padding: u32,
+}
+
+impl Default for BloomParams {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            knee: 0.2,
+            intensity: 0.6,
+            _padding: 0,
+        }
+    }
+}
+
+/// 泛光：提取亮部后在一半分辨率的两张纹理上做横向和纵向的高斯模糊，再叠加回原图
+pub struct Bloom {
+    format: wgpu::TextureFormat,
+    prefilter: FullscreenPass,
+    blur_horizontal: FullscreenPass,
+    blur_vertical: FullscreenPass,
+    composite: FullscreenPass,
+    /// 一半分辨率的模糊纹理，结果最终位于 `targets[0]`
+    targets: [Texture; 2],
+    params_buffer: wgpu::Buffer,
+    params: BloomParams,
+}
+
+impl Bloom {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        params: BloomParams,
+    ) -> Self {
+        let shader = create_shader(
+            device,
+            "Bloom Shader",
+            "bloom.wgsl",
+            include_str!("bloom.wgsl"),
+        );
+
+        let prefilter = FullscreenPass::new(
+            device,
+            "Bloom Prefilter",
+            &shader,
+            "fs_prefilter",
+            format,
+            &[uniform_entry(2)],
+        );
+        let blur_horizontal = FullscreenPass::new(
+            device,
+            "Bloom Blur Horizontal",
+            &shader,
+            "fs_blur_horizontal",
+            format,
+            &[],
+        );
+        let blur_vertical = FullscreenPass::new(
+            device,
+            "Bloom Blur Vertical",
+            &shader,
+            "fs_blur_vertical",
+            format,
+            &[],
+        );
+        let composite = FullscreenPass::new(
+            device,
+            "Bloom Composite",
+            &shader,
+            "fs_composite",
+            format,
+            &[uniform_entry(2), texture_entry(3)],
+        );
+
+        let params_buffer = create_params_buffer(device, "Bloom Params Buffer", &params);
+
+        Self {
+            format,
+            prefilter,
+            blur_horizontal,
+            blur_vertical,
+            composite,
+            targets: Self::create_targets(device, config, format),
+            params_buffer,
+            params,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+    ) -> [Texture; 2] {
+        let half = wgpu::SurfaceConfiguration {
+            width: (config.width / 2).max(1),
+            height: (config.height / 2).max(1),
+            ..config.clone()
+        };
+        [
+            Texture::create_render_target(device, &half, format, Some("Bloom Target A")),
+            Texture::create_render_target(device, &half, format, Some("Bloom Target B")),
+        ]
+    }
+
+    pub fn params(&self) -> BloomParams {
+        self.params
+    }
+
+    pub fn set_params(&mut self, queue: &wgpu::Queue, params: BloomParams) {
+        self.params = params;
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+    }
+}
+
+impl PostEffect for Bloom {
+    fn name(&self) -> &'static str {
+        "Bloom"
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.targets = Self::create_targets(device, config, self.format);
+    }
+
+    fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        output: &wgpu::TextureView,
+    ) {
+        let params = wgpu::BindGroupEntry {
+            binding: 2,
+            resource: self.params_buffer.as_entire_binding(),
+        };
+        let [a, b] = &self.targets;
+
+        self.prefilter.render(
+            device,
+            encoder,
+            input,
+            std::slice::from_ref(&params),
+            &a.view,
+        );
+        self.blur_horizontal
+            .render(device, encoder, a, &[], &b.view);
+        self.blur_vertical.render(device, encoder, b, &[], &a.view);
+        self.composite.render(
+            device,
+            encoder,
+            input,
+            &[
+                params,
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&a.view),
+                },
+            ],
+            output,
+        );
+    }
+}
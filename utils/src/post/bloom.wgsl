@@ -0,0 +1,62 @@
+#include "fullscreen.wgsl"
+
+// 泛光：提取亮部并缩小到一半分辨率，分两次做高斯模糊，最后叠加回原图
+
+struct BloomParams {
+    threshold: f32,
+    // 阈值附近的柔和过渡宽度
+    knee: f32,
+    intensity: f32,
+}
+@group(0) @binding(2)
+var<uniform> params: BloomParams;
+// 合成时使用的模糊结果
+@group(0) @binding(3)
+var t_bloom: texture_2d<f32>;
+
+// 利用线性过滤，5 次采样得到 9 个像素的高斯权重
+const BLUR_OFFSET_1: f32 = 1.3846153846;
+const BLUR_OFFSET_2: f32 = 3.2307692308;
+const BLUR_WEIGHT_0: f32 = 0.2270270270;
+const BLUR_WEIGHT_1: f32 = 0.3162162162;
+const BLUR_WEIGHT_2: f32 = 0.0702702703;
+
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4f {
+    let color = textureSampleLevel(t_input, s_input, in.uv, 0.0).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    // 二次曲线的软阈值
+    var soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    soft = soft * soft / (4.0 * params.knee + 1e-4);
+    let contribution = max(soft, brightness - params.threshold) / max(brightness, 1e-4);
+    return vec4f(color * contribution, 1.0);
+}
+
+fn blur(uv: vec2f, direction: vec2f) -> vec4f {
+    let texel = direction / vec2f(textureDimensions(t_input));
+    let offset_1 = texel * BLUR_OFFSET_1;
+    let offset_2 = texel * BLUR_OFFSET_2;
+    var color = textureSampleLevel(t_input, s_input, uv, 0.0).rgb * BLUR_WEIGHT_0;
+    color += textureSampleLevel(t_input, s_input, uv + offset_1, 0.0).rgb * BLUR_WEIGHT_1;
+    color += textureSampleLevel(t_input, s_input, uv - offset_1, 0.0).rgb * BLUR_WEIGHT_1;
+    color += textureSampleLevel(t_input, s_input, uv + offset_2, 0.0).rgb * BLUR_WEIGHT_2;
+    color += textureSampleLevel(t_input, s_input, uv - offset_2, 0.0).rgb * BLUR_WEIGHT_2;
+    return vec4f(color, 1.0);
+}
+
+@fragment
+fn fs_blur_horizontal(in: VertexOutput) -> @location(0) vec4f {
+    return blur(in.uv, vec2f(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_vertical(in: VertexOutput) -> @location(0) vec4f {
+    return blur(in.uv, vec2f(0.0, 1.0));
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4f {
+    let color = textureLoad(t_input, vec2i(in.clip_position.xy), 0);
+    let bloom = textureSampleLevel(t_bloom, s_input, in.uv, 0.0).rgb;
+    return vec4f(color.rgb + bloom * params.intensity, color.a);
+}
@@ -0,0 +1,8 @@
+#include "fullscreen.wgsl"
+
+// 没有启用任何效果时把输入原样复制到输出
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureLoad(t_input, vec2i(in.clip_position.xy), 0);
+}
@@ -0,0 +1,24 @@
+// 所有后处理效果共用的全屏三角形和输入纹理，效果的着色器通过 #include 引入
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    // 左上角为 (0, 0)，与纹理坐标一致
+    @location(0) uv: vec2f,
+};
+
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2f(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    out.clip_position = vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
@@ -0,0 +1,37 @@
+use super::{FullscreenPass, PostEffect, create_shader};
+use crate::texture::Texture;
+
+/// 快速近似抗锯齿，只依赖最终的颜色，放在色调映射之后效果最好
+pub struct Fxaa {
+    pass: FullscreenPass,
+}
+
+impl Fxaa {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = create_shader(
+            device,
+            "FXAA Shader",
+            "fxaa.wgsl",
+            include_str!("fxaa.wgsl"),
+        );
+        let pass = FullscreenPass::new(device, "FXAA", &shader, "fs_main", format, &[]);
+
+        Self { pass }
+    }
+}
+
+impl PostEffect for Fxaa {
+    fn name(&self) -> &'static str {
+        "FXAA"
+    }
+
+    fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        output: &wgpu::TextureView,
+    ) {
+        self.pass.render(device, encoder, input, &[], output);
+    }
+}
@@ -0,0 +1,53 @@
+#include "fullscreen.wgsl"
+
+// 简化的 FXAA：沿边缘方向做两次混合，混合结果超出邻域亮度范围时退回较短的那次
+
+const FXAA_REDUCE_MIN: f32 = 1.0 / 128.0;
+const FXAA_REDUCE_MUL: f32 = 1.0 / 8.0;
+const FXAA_SPAN_MAX: f32 = 8.0;
+
+fn luma(color: vec3f) -> f32 {
+    // 输入是线性颜色，开方近似到感知亮度
+    return sqrt(dot(color, vec3f(0.299, 0.587, 0.114)));
+}
+
+fn sample_input(uv: vec2f) -> vec3f {
+    return textureSampleLevel(t_input, s_input, uv, 0.0).rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let texel = 1.0 / vec2f(textureDimensions(t_input));
+    let center = textureSampleLevel(t_input, s_input, in.uv, 0.0);
+
+    let luma_nw = luma(sample_input(in.uv + vec2f(-1.0, -1.0) * texel));
+    let luma_ne = luma(sample_input(in.uv + vec2f(1.0, -1.0) * texel));
+    let luma_sw = luma(sample_input(in.uv + vec2f(-1.0, 1.0) * texel));
+    let luma_se = luma(sample_input(in.uv + vec2f(1.0, 1.0) * texel));
+    let luma_m = luma(center.rgb);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // 亮度梯度的垂直方向就是边缘方向
+    var dir = vec2f(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    let rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2f(-FXAA_SPAN_MAX), vec2f(FXAA_SPAN_MAX)) * texel;
+
+    let rgb_a = 0.5 * (
+        sample_input(in.uv + dir * (1.0 / 3.0 - 0.5)) +
+        sample_input(in.uv + dir * (2.0 / 3.0 - 0.5))
+    );
+    let rgb_b = rgb_a * 0.5 + 0.25 * (
+        sample_input(in.uv - dir * 0.5) +
+        sample_input(in.uv + dir * 0.5)
+    );
+
+    let luma_b = luma(rgb_b);
+    let color = select(rgb_b, rgb_a, luma_b < luma_min || luma_b > luma_max);
+    return vec4f(color, center.a);
+}
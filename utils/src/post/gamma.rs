@@ -0,0 +1,93 @@
+use bytemuck::{Pod, Zeroable};
+
+use super::{FullscreenPass, PostEffect, create_params_buffer, create_shader, uniform_entry};
+use crate::texture::Texture;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GammaParams {
+    inv_gamma: f32,
+    _padding: [u32; 3],
+}
+
+/// 伽马校正：输出 `color^(1 / gamma)`
+///
+/// 表面格式是 sRGB 时硬件已经做了编码，`gamma` 只用于微调明暗，保持 1 即为原样输出；
+/// 表面格式不是 sRGB 时可以设为 2.2 近似 sRGB 编码
+pub struct Gamma {
+    pass: FullscreenPass,
+    params_buffer: wgpu::Buffer,
+    gamma: f32,
+}
+
+impl Gamma {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, gamma: f32) -> Self {
+        let shader = create_shader(
+            device,
+            "Gamma Shader",
+            "gamma.wgsl",
+            include_str!("gamma.wgsl"),
+        );
+        let pass = FullscreenPass::new(
+            device,
+            "Gamma",
+            &shader,
+            "fs_main",
+            format,
+            &[uniform_entry(2)],
+        );
+        let params_buffer =
+            create_params_buffer(device, "Gamma Params Buffer", &Self::params(gamma));
+
+        Self {
+            pass,
+            params_buffer,
+            gamma,
+        }
+    }
+
+    fn params(gamma: f32) -> GammaParams {
+        GammaParams {
+            inv_gamma: 1.0 / gamma,
+            _padding: [0; 3],
+        }
+    }
+
+    pub fn gamma(&self) -> f32 {
+        self.gamma
+    }
+
+    pub fn set_gamma(&mut self, queue: &wgpu::Queue, gamma: f32) {
+        self.gamma = gamma;
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::bytes_of(&Self::params(gamma)),
+        );
+    }
+}
+
+impl PostEffect for Gamma {
+    fn name(&self) -> &'static str {
+        "Gamma"
+    }
+
+    fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        output: &wgpu::TextureView,
+    ) {
+        self.pass.render(
+            device,
+            encoder,
+            input,
+            &[wgpu::BindGroupEntry {
+                binding: 2,
+                resource: self.params_buffer.as_entire_binding(),
+            }],
+            output,
+        );
+    }
+}
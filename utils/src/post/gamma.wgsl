@@ -0,0 +1,15 @@
+#include "fullscreen.wgsl"
+
+// 在表面的 sRGB 编码之外再做一次幂函数校正
+
+struct GammaParams {
+    inv_gamma: f32,
+}
+@group(0) @binding(2)
+var<uniform> params: GammaParams;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let color = textureLoad(t_input, vec2i(in.clip_position.xy), 0);
+    return vec4f(pow(max(color.rgb, vec3f(0.0)), vec3f(params.inv_gamma)), color.a);
+}
@@ -0,0 +1,281 @@
+use std::path::Path;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+use super::{FullscreenPass, PostEffect, create_params_buffer, create_shader, uniform_entry};
+use crate::texture::{Texture, TextureError};
+
+/// 颜色分级用的三维查找表，坐标和内容都是 sRGB 编码的颜色
+pub struct ColorLut {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    /// 每个颜色通道的采样数
+    pub size: u32,
+}
+
+impl ColorLut {
+    /// 对每个格点调用 `f` 生成查找表
+    pub fn from_fn(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        f: impl Fn(Vec3) -> Vec3,
+    ) -> Self {
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let color = f(Vec3::new(r as f32, g as f32, b as f32) / max);
+                    let color = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0).round();
+                    data.extend_from_slice(&[color.x as u8, color.y as u8, color.z as u8, 255]);
+                }
+            }
+        }
+        Self::from_volume(device, queue, size, &data)
+    }
+
+    /// 不改变颜色的查找表
+    pub fn identity(device: &wgpu::Device, queue: &wgpu::Queue, size: u32) -> Self {
+        Self::from_fn(device, queue, size, |color| color)
+    }
+
+    /// 从常见的横条格式读取：`size` 个 `size x size` 的切片从左到右排列，
+    /// 切片内 x 方向是红色，y 方向是绿色，切片的序号是蓝色
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &image::RgbaImage,
+    ) -> Result<Self, TextureError> {
+        let (size, data) = strip_to_volume(image).ok_or(TextureError::InvalidLutSize {
+            width: image.width(),
+            height: image.height(),
+        })?;
+        Ok(Self::from_volume(device, queue, size, &data))
+    }
+
+    pub fn from_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, TextureError> {
+        let image = image::open(path)?.to_rgba8();
+        Self::from_image(device, queue, &image)
+    }
+
+    fn from_volume(device: &wgpu::Device, queue: &wgpu::Queue, size: u32, data: &[u8]) -> Self {
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color LUT"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size),
+                rows_per_image: Some(size),
+            },
+            extent,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            size,
+        }
+    }
+}
+
+/// 把横条格式的查找表重新排列成三维纹理的数据，尺寸不是 `size^2 x size` 时返回 `None`
+fn strip_to_volume(image: &image::RgbaImage) -> Option<(u32, Vec<u8>)> {
+    let size = image.height();
+    if size < 2 || image.width() != size * size {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                data.extend_from_slice(&image.get_pixel(b * size + r, g).0);
+            }
+        }
+    }
+    Some((size, data))
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GradingParams {
+    size: f32,
+    intensity: f32,
+    linear_input: u32,
+    _padding: u32,
+}
+
+/// 用 [`ColorLut`] 做颜色分级
+pub struct ColorGrading {
+    pass: FullscreenPass,
+    lut: ColorLut,
+    params_buffer: wgpu::Buffer,
+    linear_input: bool,
+    intensity: f32,
+}
+
+impl ColorGrading {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        lut: ColorLut,
+        intensity: f32,
+    ) -> Self {
+        let shader = create_shader(
+            device,
+            "Color Grading Shader",
+            "grading.wgsl",
+            include_str!("grading.wgsl"),
+        );
+        let pass = FullscreenPass::new(
+            device,
+            "Color Grading",
+            &shader,
+            "fs_main",
+            format,
+            &[
+                uniform_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+        );
+
+        // sRGB 格式的纹理在着色器中读到的是线性颜色
+        let linear_input = format.is_srgb();
+        let params_buffer = create_params_buffer(
+            device,
+            "Color Grading Params Buffer",
+            &Self::params(&lut, intensity, linear_input),
+        );
+
+        Self {
+            pass,
+            lut,
+            params_buffer,
+            linear_input,
+            intensity,
+        }
+    }
+
+    fn params(lut: &ColorLut, intensity: f32, linear_input: bool) -> GradingParams {
+        GradingParams {
+            size: lut.size as f32,
+            intensity,
+            linear_input: linear_input as u32,
+            _padding: 0,
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    pub fn set_intensity(&mut self, queue: &wgpu::Queue, intensity: f32) {
+        self.intensity = intensity;
+        self.write_params(queue);
+    }
+
+    pub fn set_lut(&mut self, queue: &wgpu::Queue, lut: ColorLut) {
+        self.lut = lut;
+        self.write_params(queue);
+    }
+
+    fn write_params(&self, queue: &wgpu::Queue) {
+        let params = Self::params(&self.lut, self.intensity, self.linear_input);
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+    }
+}
+
+impl PostEffect for ColorGrading {
+    fn name(&self) -> &'static str {
+        "Color Grading"
+    }
+
+    fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        output: &wgpu::TextureView,
+    ) {
+        self.pass.render(
+            device,
+            encoder,
+            input,
+            &[
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&self.lut.view),
+                },
+            ],
+            output,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_is_rearranged_into_volume() {
+        let size = 4;
+        let image = image::RgbaImage::from_fn(size * size, size, |x, y| {
+            image::Rgba([(x % size) as u8, y as u8, (x / size) as u8, 255])
+        });
+
+        let (lut_size, data) = strip_to_volume(&image).unwrap();
+        assert_eq!(lut_size, size);
+        for (i, texel) in data.chunks_exact(4).enumerate() {
+            let i = i as u32;
+            let expected = [i % size, i / size % size, i / (size * size)].map(|c| c as u8);
+            assert_eq!(&texel[..3], &expected);
+        }
+    }
+
+    #[test]
+    fn strip_with_wrong_size_is_rejected() {
+        assert!(strip_to_volume(&image::RgbaImage::new(16, 16)).is_none());
+    }
+}
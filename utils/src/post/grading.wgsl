@@ -0,0 +1,47 @@
+#include "fullscreen.wgsl"
+
+// 用三维查找表做颜色分级，查找表以 sRGB 编码的颜色为坐标
+
+struct GradingParams {
+    // 查找表的边长
+    size: f32,
+    // 与原色混合的比例，1 表示完全使用查找表的结果
+    intensity: f32,
+    // 输入是否是线性颜色，是的话查找前需要先编码成 sRGB
+    linear_input: u32,
+}
+@group(0) @binding(2)
+var<uniform> params: GradingParams;
+@group(0) @binding(3)
+var t_lut: texture_3d<f32>;
+
+fn linear_to_srgb(color: vec3f) -> vec3f {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3f(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3f(0.0031308));
+}
+
+fn srgb_to_linear(color: vec3f) -> vec3f {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3f(2.4));
+    return select(high, low, color <= vec3f(0.04045));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let color = textureLoad(t_input, vec2i(in.clip_position.xy), 0);
+    var encoded = saturate(color.rgb);
+    if params.linear_input != 0u {
+        encoded = linear_to_srgb(encoded);
+    }
+
+    // 把 [0, 1] 映射到第一个和最后一个像素的中心，避免边缘与夹取的像素混合
+    let scale = (params.size - 1.0) / params.size;
+    let offset = 0.5 / params.size;
+    var graded = textureSampleLevel(t_lut, s_input, encoded * scale + offset, 0.0).rgb;
+    if params.linear_input != 0u {
+        graded = srgb_to_linear(graded);
+    }
+
+    return vec4f(mix(color.rgb, graded, params.intensity), color.a);
+}
@@ -0,0 +1,94 @@
+use bytemuck::{Pod, Zeroable};
+
+use super::{FullscreenPass, PostEffect, create_params_buffer, create_shader, uniform_entry};
+use crate::texture::Texture;
+
+/// 暗角的参数，距离以画面中心为 0、角落为 1
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct VignetteParams {
+    /// 角落处变暗的比例
+    pub intensity: f32,
+    /// 开始变暗的距离
+    pub radius: f32,
+    /// 从开始变暗到完全变暗的过渡距离
+    pub smoothness: f32,
+    pub _padding: u32,
+}
+
+impl Default for VignetteParams {
+    fn default() -> Self {
+        Self {
+            intensity: 0.5,
+            radius: 0.5,
+            smoothness: 0.5,
+            _padding: 0,
+        }
+    }
+}
+
+pub struct Vignette {
+    pass: FullscreenPass,
+    params_buffer: wgpu::Buffer,
+    params: VignetteParams,
+}
+
+impl Vignette {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, params: VignetteParams) -> Self {
+        let shader = create_shader(
+            device,
+            "Vignette Shader",
+            "vignette.wgsl",
+            include_str!("vignette.wgsl"),
+        );
+        let pass = FullscreenPass::new(
+            device,
+            "Vignette",
+            &shader,
+            "fs_main",
+            format,
+            &[uniform_entry(2)],
+        );
+        let params_buffer = create_params_buffer(device, "Vignette Params Buffer", &params);
+
+        Self {
+            pass,
+            params_buffer,
+            params,
+        }
+    }
+
+    pub fn params(&self) -> VignetteParams {
+        self.params
+    }
+
+    pub fn set_params(&mut self, queue: &wgpu::Queue, params: VignetteParams) {
+        self.params = params;
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+    }
+}
+
+impl PostEffect for Vignette {
+    fn name(&self) -> &'static str {
+        "Vignette"
+    }
+
+    fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &Texture,
+        output: &wgpu::TextureView,
+    ) {
+        self.pass.render(
+            device,
+            encoder,
+            input,
+            &[wgpu::BindGroupEntry {
+                binding: 2,
+                resource: self.params_buffer.as_entire_binding(),
+            }],
+            output,
+        );
+    }
+}
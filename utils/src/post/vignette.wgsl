@@ -0,0 +1,20 @@
+#include "fullscreen.wgsl"
+
+// 暗角：从 radius 开始向画面边缘逐渐变暗
+
+struct VignetteParams {
+    intensity: f32,
+    radius: f32,
+    smoothness: f32,
+}
+@group(0) @binding(2)
+var<uniform> params: VignetteParams;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let color = textureLoad(t_input, vec2i(in.clip_position.xy), 0);
+    // 归一化到画面中心为 0、角落为 1
+    let distance = length(in.uv - 0.5) * sqrt(2.0);
+    let falloff = smoothstep(params.radius, params.radius + params.smoothness, distance);
+    return vec4f(color.rgb * (1.0 - params.intensity * falloff), color.a);
+}
@@ -0,0 +1,462 @@
+//! WGSL 预处理器。
+//!
+//! 以 `#` 开头的行是指令，其余的行原样输出：
+//! - `#include "common.wgsl"` 引入另一个文件，路径相对于当前文件；同一个文件只会被引入一次
+//! - `#define NAME` / `#define NAME value` / `#undef NAME`，带值的宏在之后的代码中按标识符替换，`//` 注释中的文字不替换
+//! - `#ifdef NAME` / `#ifndef NAME` / `#else` / `#endif` 按宏是否定义选择代码
+//!
+//! 输出的每一行都记录了来源的文件和行号，着色器编译出错时可以定位到原始文件。
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum PreprocessError {
+    Io {
+        file: String,
+        error: std::io::Error,
+    },
+    /// 找不到被引入的文件
+    MissingFile {
+        file: String,
+        line: u32,
+        include: String,
+    },
+    /// 无法识别的指令，或者 `#ifdef` 与 `#endif` 不配对
+    Syntax {
+        file: String,
+        line: u32,
+        message: String,
+    },
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessError::Io { file, error } => write!(f, "failed to read {file}: {error}"),
+            PreprocessError::MissingFile {
+                file,
+                line,
+                include,
+            } => write!(f, "{file}:{line}: cannot find included file \"{include}\""),
+            PreprocessError::Syntax {
+                file,
+                line,
+                message,
+            } => write!(f, "{file}:{line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// 预处理后的着色器源码
+#[derive(Debug, Clone)]
+pub struct ProcessedShader {
+    pub source: String,
+    /// 参与预处理的文件，第一个是入口文件
+    files: Vec<String>,
+    /// 输出的第 `i` 行来自 `files[lines[i].0]` 的第 `lines[i].1` 行
+    lines: Vec<(usize, u32)>,
+}
+
+impl ProcessedShader {
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// 输出中从 1 开始的行号对应的原始文件和行号
+    pub fn map_line(&self, line: u32) -> Option<(&str, u32)> {
+        let &(file, original) = self.lines.get(line.checked_sub(1)? as usize)?;
+        Some((&self.files[file], original))
+    }
+
+    /// 把输出中 `line:column` 处的错误写成 `file:line:column: message`，并附上原始的那一行
+    pub fn format_error(&self, location: Option<(u32, u32)>, message: &str) -> String {
+        let Some((line, column)) = location else {
+            return format!("{}: {message}", self.files[0]);
+        };
+        let Some((file, original)) = self.map_line(line) else {
+            return format!("{}: {message}", self.files[0]);
+        };
+        let text = self.source.lines().nth(line as usize - 1).unwrap_or("");
+        format!("{file}:{original}:{column}: {message}\n    | {text}")
+    }
+}
+
+/// 各个示例共用的顶点着色器输入输出，通过 [`Preprocessor::with_common`] 注册为 `common.wgsl`
+pub const COMMON_WGSL: &str = include_str!("common.wgsl");
+
+/// 预处理器，被引入的文件从通过 [`Preprocessor::with_file`] 注册的源码中查找，
+/// 设置了 [`Preprocessor::with_root`] 时优先从磁盘读取，磁盘上没有的文件再查找注册的源码
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessor {
+    files: HashMap<String, String>,
+    root: Option<PathBuf>,
+    defines: HashMap<String, String>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册一个可以被引入的文件，通常与 `include_str!` 一起使用
+    pub fn with_file(mut self, name: &str, source: &str) -> Self {
+        self.files.insert(normalize(name), source.to_string());
+        self
+    }
+
+    /// `name` 是否已经通过 [`Preprocessor::with_file`] 注册
+    pub fn has_file(&self, name: &str) -> bool {
+        self.files.contains_key(&normalize(name))
+    }
+
+    /// 注册 [`COMMON_WGSL`]，着色器通过 `#include "common.wgsl"` 引入
+    pub fn with_common(self) -> Self {
+        self.with_file("common.wgsl", COMMON_WGSL)
+    }
+
+    /// 从 `root` 目录读取文件，用于热重载
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    pub fn with_define(mut self, name: &str) -> Self {
+        self.define(name, "");
+        self
+    }
+
+    /// 定义一个宏，`value` 为空时只作为开关
+    pub fn define(&mut self, name: &str, value: &str) {
+        self.defines.insert(name.to_string(), value.to_string());
+    }
+
+    /// 预处理入口文件 `name`
+    pub fn process(&self, name: &str) -> Result<ProcessedShader, PreprocessError> {
+        let mut state = State {
+            output: ProcessedShader {
+                source: String::new(),
+                files: Vec::new(),
+                lines: Vec::new(),
+            },
+            included: HashSet::new(),
+            defines: self.defines.clone(),
+        };
+        let name = normalize(name);
+        let source = self.load(&name).map_err(|error| PreprocessError::Io {
+            file: name.clone(),
+            error,
+        })?;
+        self.process_file(&mut state, name, &source)?;
+        Ok(state.output)
+    }
+
+    /// 预处理编译进程序的着色器并创建模块，预处理失败说明代码有误，会直接 panic
+    pub fn create_shader_module(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        name: &str,
+    ) -> wgpu::ShaderModule {
+        let shader = self
+            .process(name)
+            .unwrap_or_else(|e| panic!("{label}: {e}"));
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(shader.source.into()),
+        })
+    }
+
+    fn load(&self, name: &str) -> std::io::Result<String> {
+        if let Some(root) = &self.root {
+            match std::fs::read_to_string(root.join(name)) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                result => return result,
+            }
+        }
+        self.files.get(name).cloned().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "file is not registered")
+        })
+    }
+
+    fn process_file(
+        &self,
+        state: &mut State,
+        name: String,
+        source: &str,
+    ) -> Result<(), PreprocessError> {
+        state.included.insert(name.clone());
+        let file_index = state.output.files.len();
+        state.output.files.push(name.clone());
+
+        // 每一层条件的 (当前是否输出, 是否已经遇到 #else, 开始的行号)
+        let mut conditions: Vec<(bool, bool, u32)> = Vec::new();
+        let syntax = |line: u32, message: &str| PreprocessError::Syntax {
+            file: name.clone(),
+            line,
+            message: message.to_string(),
+        };
+
+        for (index, text) in source.lines().enumerate() {
+            let line = index as u32 + 1;
+            let active = conditions.iter().all(|&(active, _, _)| active);
+            let Some(directive) = text.trim_start().strip_prefix('#') else {
+                if active {
+                    state.push_line(file_index, line, text);
+                }
+                continue;
+            };
+
+            let (keyword, argument) = directive
+                .trim()
+                .split_once(char::is_whitespace)
+                .map_or((directive.trim(), ""), |(k, a)| (k, a.trim()));
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let defined = state.defines.contains_key(argument);
+                    conditions.push((defined == (keyword == "ifdef"), false, line));
+                }
+                "else" => match conditions.last_mut() {
+                    Some((active, seen_else @ false, _)) => {
+                        *active = !*active;
+                        *seen_else = true;
+                    }
+                    Some(_) => return Err(syntax(line, "duplicate #else")),
+                    None => return Err(syntax(line, "#else without #ifdef")),
+                },
+                "endif" => {
+                    if conditions.pop().is_none() {
+                        return Err(syntax(line, "#endif without #ifdef"));
+                    }
+                }
+                _ if !active => {}
+                "define" => {
+                    let (macro_name, value) = argument
+                        .split_once(char::is_whitespace)
+                        .map_or((argument, ""), |(n, v)| (n, v.trim()));
+                    if macro_name.is_empty() {
+                        return Err(syntax(line, "#define without a name"));
+                    }
+                    state
+                        .defines
+                        .insert(macro_name.to_string(), value.to_string());
+                }
+                "undef" => {
+                    state.defines.remove(argument);
+                }
+                "include" => {
+                    let Some(include) = argument
+                        .strip_prefix('"')
+                        .and_then(|rest| rest.strip_suffix('"'))
+                    else {
+                        return Err(syntax(line, "expected #include \"file\""));
+                    };
+                    let path = resolve(&name, include);
+                    if state.included.contains(&path) {
+                        continue;
+                    }
+                    let source = self.load(&path).map_err(|error| {
+                        if error.kind() == std::io::ErrorKind::NotFound {
+                            PreprocessError::MissingFile {
+                                file: name.clone(),
+                                line,
+                                include: include.to_string(),
+                            }
+                        } else {
+                            PreprocessError::Io {
+                                file: path.clone(),
+                                error,
+                            }
+                        }
+                    })?;
+                    self.process_file(state, path, &source)?;
+                }
+                _ => return Err(syntax(line, &format!("unknown directive #{keyword}"))),
+            }
+        }
+
+        match conditions.last() {
+            Some(&(_, _, line)) => Err(syntax(line, "#ifdef without #endif")),
+            None => Ok(()),
+        }
+    }
+}
+
+struct State {
+    output: ProcessedShader,
+    included: HashSet<String>,
+    defines: HashMap<String, String>,
+}
+
+impl State {
+    fn push_line(&mut self, file: usize, line: u32, text: &str) {
+        let text = substitute(text, &self.defines);
+        self.output.source.push_str(&text);
+        self.output.source.push('\n');
+        self.output.lines.push((file, line));
+    }
+}
+
+/// 把带值的宏按完整的标识符替换，`//` 之后的注释原样保留
+fn substitute(text: &str, defines: &HashMap<String, String>) -> String {
+    if defines.values().all(String::is_empty) {
+        return text.to_string();
+    }
+
+    let (code, comment) = text.split_at(text.find("//").unwrap_or(text.len()));
+    let mut out = String::with_capacity(text.len());
+    let mut rest = code;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let identifier = &rest[..end];
+        match defines.get(identifier) {
+            Some(value) if !value.is_empty() => out.push_str(value),
+            _ => out.push_str(identifier),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out.push_str(comment);
+    out
+}
+
+/// 相对于 `from` 所在目录解析 `include`
+fn resolve(from: &str, include: &str) -> String {
+    match from.rsplit_once('/') {
+        Some((dir, _)) => normalize(&format!("{dir}/{include}")),
+        None => normalize(include),
+    }
+}
+
+/// 去掉路径中的 `.` 和 `..`，统一使用 `/` 分隔
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." if parts.last().is_some_and(|&last| last != "..") => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn includes_are_expanded_once_and_lines_are_mapped() {
+        let shader = Preprocessor::new()
+            .with_file(
+                "shaders/main.wgsl",
+                "#include \"common.wgsl\"\n#include \"./common.wgsl\"\nfn main() {}",
+            )
+            .with_file("shaders/common.wgsl", "// common\nstruct A { x: f32 }")
+            .process("shaders/main.wgsl")
+            .unwrap();
+
+        assert_eq!(
+            shader.source,
+            "// common\nstruct A { x: f32 }\nfn main() {}\n"
+        );
+        assert_eq!(shader.files(), ["shaders/main.wgsl", "shaders/common.wgsl"]);
+        assert_eq!(shader.map_line(2), Some(("shaders/common.wgsl", 2)));
+        assert_eq!(shader.map_line(3), Some(("shaders/main.wgsl", 3)));
+        assert_eq!(shader.map_line(4), None);
+    }
+
+    #[test]
+    fn defines_select_code_and_substitute_values() {
+        let source =
+            "#define COUNT 4\n#ifdef PBR\npbr(COUNT);\n#else\nphong(COUNT, COUNTER);\n#endif";
+        let processor = Preprocessor::new().with_file("a.wgsl", source);
+
+        assert_eq!(
+            processor.process("a.wgsl").unwrap().source,
+            "phong(4, COUNTER);\n"
+        );
+        assert_eq!(
+            processor
+                .with_define("PBR")
+                .process("a.wgsl")
+                .unwrap()
+                .source,
+            "pbr(4);\n"
+        );
+    }
+
+    #[test]
+    fn comments_are_not_substituted() {
+        let shader = Preprocessor::new()
+            .with_file(
+                "a.wgsl",
+                "#define N 4
+let n = N; // N lights",
+            )
+            .process("a.wgsl")
+            .unwrap();
+        assert_eq!(shader.source, "let n = 4; // N lights\n");
+    }
+
+    #[test]
+    fn registered_files_are_used_when_missing_from_root() {
+        let dir = crate::headless::TempDir::new("utils-preprocess");
+        std::fs::write(
+            dir.join("main.wgsl"),
+            "#include \"common.wgsl\"\nfn main() {}",
+        )
+        .unwrap();
+        let shader = Preprocessor::new()
+            .with_common()
+            .with_root(dir.path())
+            .process("main.wgsl")
+            .unwrap();
+        assert!(shader.source.contains("struct VertexOutput"));
+        assert_eq!(shader.files(), ["main.wgsl", "common.wgsl"]);
+    }
+
+    #[test]
+    fn errors_name_the_original_file() {
+        let processor = Preprocessor::new()
+            .with_file("a.wgsl", "#include \"b.wgsl\"")
+            .with_file(
+                "b.wgsl",
+                "\n#ifdef X\n#include \"missing.wgsl\"\n#endif\n#ifndef X",
+            );
+
+        let error = processor.process("a.wgsl").unwrap_err().to_string();
+        assert_eq!(error, "b.wgsl:5: #ifdef without #endif");
+
+        let error = processor
+            .with_define("X")
+            .process("a.wgsl")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "b.wgsl:3: cannot find included file \"missing.wgsl\""
+        );
+    }
+
+    #[test]
+    fn paths_are_resolved_relative_to_the_including_file() {
+        assert_eq!(resolve("a/b/main.wgsl", "../common.wgsl"), "a/common.wgsl");
+        assert_eq!(resolve("main.wgsl", "lib/x.wgsl"), "lib/x.wgsl");
+    }
+}
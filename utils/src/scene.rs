@@ -0,0 +1,884 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use glam::Mat4;
+
+use crate::{
+    mipmap::MipmapGenerator,
+    model::{Material, Mesh, ModelVertex, compute_normals, compute_tangents, solid_color_texture},
+    pbr::{PbrFactors, PbrMaps, PbrMaterial},
+    texture::{Texture, TextureError, TextureOptions},
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Gltf(gltf::Error),
+    Texture(TextureError),
+    /// 图元缺少 `POSITION` 属性
+    MissingPositions {
+        mesh: usize,
+        primitive: usize,
+    },
+    /// 只支持每个通道 8 位或 16 位的整数图片
+    UnsupportedImageFormat(gltf::image::Format),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Gltf(e) => write!(f, "failed to load gltf: {e}"),
+            SceneError::Texture(e) => write!(f, "failed to load material texture: {e}"),
+            SceneError::MissingPositions { mesh, primitive } => write!(
+                f,
+                "primitive {primitive} of mesh {mesh} has no POSITION attribute"
+            ),
+            SceneError::UnsupportedImageFormat(format) => {
+                write!(f, "unsupported image format: {format:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<gltf::Error> for SceneError {
+    fn from(e: gltf::Error) -> Self {
+        SceneError::Gltf(e)
+    }
+}
+
+impl From<TextureError> for SceneError {
+    fn from(e: TextureError) -> Self {
+        SceneError::Texture(e)
+    }
+}
+
+/// glTF 材质同时提供两种绑定组
+///
+/// `material` 只包含基础颜色纹理，可以直接用于 [`crate::model::DrawModel`] 的管线，
+/// 没有基础颜色纹理时用基础颜色系数生成 1x1 纹理；`pbr` 包含全部贴图和系数
+pub struct SceneMaterial {
+    pub material: Material,
+    pub pbr: PbrMaterial,
+}
+
+/// 导入场景时创建材质绑定组所需的布局
+pub struct MaterialLayouts {
+    pub basic: wgpu::BindGroupLayout,
+    pub pbr: wgpu::BindGroupLayout,
+}
+
+impl MaterialLayouts {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            basic: Material::bind_group_layout(device),
+            pbr: PbrMaterial::bind_group_layout(device),
+        }
+    }
+}
+
+/// glTF 网格，每个图元对应一个 [`Mesh`]
+pub struct SceneMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<Mesh>,
+}
+
+pub struct Node {
+    pub name: Option<String>,
+    /// 相对于父节点的变换
+    pub transform: Mat4,
+    /// 在 [`Scene::meshes`] 中的下标
+    pub mesh: Option<usize>,
+    /// 子节点在 [`Scene::nodes`] 中的下标
+    pub children: Vec<usize>,
+}
+
+/// 从 glTF 2.0 导入的场景图
+pub struct Scene {
+    pub nodes: Vec<Node>,
+    /// 默认场景的根节点
+    pub roots: Vec<usize>,
+    pub meshes: Vec<SceneMesh>,
+    pub materials: Vec<SceneMaterial>,
+}
+
+impl Scene {
+    /// 读取 `.gltf` 或 `.glb` 文件，外部的缓冲区和图片路径相对于该文件所在目录
+    pub fn load_gltf(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        path: impl AsRef<Path>,
+        layouts: &MaterialLayouts,
+    ) -> Result<Self, SceneError> {
+        let (document, buffers, images) = gltf::import(path)?;
+        Self::from_gltf(
+            device, queue, mipmaps, &document, &buffers, &images, layouts,
+        )
+    }
+
+    /// 从内存中的 `.glb` 或只使用内嵌数据的 `.gltf` 导入
+    pub fn from_slice(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        bytes: &[u8],
+        layouts: &MaterialLayouts,
+    ) -> Result<Self, SceneError> {
+        let (document, buffers, images) = gltf::import_slice(bytes)?;
+        Self::from_gltf(
+            device, queue, mipmaps, &document, &buffers, &images, layouts,
+        )
+    }
+
+    fn from_gltf(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        document: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+        images: &[gltf::image::Data],
+        layouts: &MaterialLayouts,
+    ) -> Result<Self, SceneError> {
+        let mut textures = TextureCache::new();
+        let mut materials = document
+            .materials()
+            .map(|m| load_material(device, queue, mipmaps, &m, images, &mut textures, layouts))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // 没有指定材质的图元使用 glTF 规定的默认材质
+        let default_material = materials.len();
+        let default = document
+            .meshes()
+            .flat_map(|m| m.primitives())
+            .find(|p| p.material().index().is_none());
+        if let Some(primitive) = default {
+            // 没有材质的图元的 `material()` 返回的就是默认材质
+            materials.push(load_material(
+                device,
+                queue,
+                mipmaps,
+                &primitive.material(),
+                images,
+                &mut textures,
+                layouts,
+            )?);
+        }
+
+        let meshes = document
+            .meshes()
+            .map(|mesh| {
+                let mut primitives = Vec::new();
+                for primitive in mesh.primitives() {
+                    if primitive.mode() != gltf::mesh::Mode::Triangles {
+                        log::warn!(
+                            "Skipping primitive {} of mesh {}: unsupported mode {:?}",
+                            primitive.index(),
+                            mesh.index(),
+                            primitive.mode()
+                        );
+                        continue;
+                    }
+
+                    let (vertices, indices) = primitive_geometry(&mesh, &primitive, buffers)?;
+                    let name = format!("{}#{}", mesh.name().unwrap_or("Mesh"), primitive.index());
+                    let material = primitive.material().index().unwrap_or(default_material);
+                    primitives.push(Mesh::new(device, &name, &vertices, &indices, material));
+                }
+
+                Ok(SceneMesh {
+                    name: mesh.name().map(str::to_string),
+                    primitives,
+                })
+            })
+            .collect::<Result<Vec<_>, SceneError>>()?;
+
+        let nodes = document
+            .nodes()
+            .map(|node| Node {
+                name: node.name().map(str::to_string),
+                transform: Mat4::from_cols_array_2d(&node.transform().matrix()),
+                mesh: node.mesh().map(|m| m.index()),
+                children: node.children().map(|c| c.index()).collect(),
+            })
+            .collect();
+
+        let roots = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .map(|scene| scene.nodes().map(|n| n.index()).collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            nodes,
+            roots,
+            meshes,
+            materials,
+        })
+    }
+
+    /// 每个节点在世界空间中的变换，下标与 [`Scene::nodes`] 一致
+    ///
+    /// 不在默认场景中的节点保持单位矩阵
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        let mut transforms = vec![Mat4::IDENTITY; self.nodes.len()];
+        let mut stack: Vec<_> = self.roots.iter().map(|&i| (i, Mat4::IDENTITY)).collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            let world = parent * node.transform;
+            transforms[index] = world;
+            stack.extend(node.children.iter().map(|&c| (c, world)));
+        }
+        transforms
+    }
+
+    /// 默认场景中带网格的节点，按深度优先顺序排列
+    pub fn mesh_nodes(&self) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut stack: Vec<_> = self.roots.iter().rev().copied().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.mesh.is_some() {
+                result.push(index);
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        result
+    }
+}
+
+/// 已经上传的图片，键为图片下标和是否使用线性格式
+///
+/// 多个材质或同一材质的多张贴图引用同一张图片时只上传一次；采样器属于 glTF 纹理而不是图片，
+/// 每次取用时按各自的采样器重新创建
+type TextureCache = HashMap<(usize, bool), Texture>;
+
+fn load_material(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mipmaps: &MipmapGenerator,
+    material: &gltf::Material,
+    images: &[gltf::image::Data],
+    textures: &mut TextureCache,
+    layouts: &MaterialLayouts,
+) -> Result<SceneMaterial, SceneError> {
+    let name = material.name().unwrap_or("Material");
+    let pbr = material.pbr_metallic_roughness();
+
+    // 颜色贴图使用 sRGB，其余的数据贴图使用线性格式
+    let mut load =
+        |texture: Option<gltf::Texture>, options: TextureOptions| -> Result<_, SceneError> {
+            let Some(texture) = texture else {
+                return Ok(None);
+            };
+            let index = texture.source().index();
+            let sampler = sampler_descriptor(&texture.sampler());
+            if let Some(cached) = textures.get(&(index, options.linear)) {
+                return Ok(Some(Texture {
+                    sampler: device.create_sampler(&sampler),
+                    ..cached.clone()
+                }));
+            }
+
+            let image = &images[index];
+            let texture = Texture::from_rgba(
+                device,
+                queue,
+                &to_rgba8(image)?,
+                image.width,
+                image.height,
+                Some(name),
+                TextureOptions {
+                    sampler: Some(&sampler),
+                    ..options
+                },
+            )?;
+            textures.insert((index, options.linear), texture.clone());
+            Ok(Some(texture))
+        };
+
+    let maps = PbrMaps {
+        base_color: load(
+            pbr.base_color_texture().map(|i| i.texture()),
+            TextureOptions::mipmapped(mipmaps),
+        )?,
+        metallic_roughness: load(
+            pbr.metallic_roughness_texture().map(|i| i.texture()),
+            TextureOptions::linear_mipmapped(mipmaps),
+        )?,
+        normal: load(
+            material.normal_texture().map(|i| i.texture()),
+            TextureOptions::linear_mipmapped(mipmaps),
+        )?,
+        occlusion: load(
+            material.occlusion_texture().map(|i| i.texture()),
+            TextureOptions::linear_mipmapped(mipmaps),
+        )?,
+        emissive: load(
+            material.emissive_texture().map(|i| i.texture()),
+            TextureOptions::mipmapped(mipmaps),
+        )?,
+    };
+
+    let factors = PbrFactors {
+        base_color: pbr.base_color_factor(),
+        emissive: material.emissive_factor(),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        normal_scale: material.normal_texture().map_or(1.0, |n| n.scale()),
+        occlusion_strength: material.occlusion_texture().map_or(1.0, |o| o.strength()),
+        alpha_cutoff: match material.alpha_mode() {
+            gltf::material::AlphaMode::Mask => material.alpha_cutoff().unwrap_or(0.5),
+            _ => 0.0,
+        },
+    };
+
+    let base_color_texture = match &maps.base_color {
+        Some(texture) => texture.clone(),
+        None => solid_color_texture(device, queue, name, factors.base_color)?,
+    };
+
+    Ok(SceneMaterial {
+        material: Material::new(device, name, base_color_texture, &layouts.basic),
+        pbr: PbrMaterial::new(device, queue, &layouts.pbr, name, factors, maps)?,
+    })
+}
+
+/// glTF 采样器的寻址和过滤方式，没有指定的过滤方式使用线性过滤
+///
+/// 纹理总是带有 mip 链，不使用 mipmap 的缩小过滤方式通过把 LOD 限制在 0 实现
+fn sampler_descriptor(sampler: &gltf::texture::Sampler) -> wgpu::SamplerDescriptor<'static> {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        Some(MagFilter::Linear) | None => wgpu::FilterMode::Linear,
+    };
+    let (min_filter, mipmap_filter, lod_max_clamp) = match sampler.min_filter() {
+        Some(MinFilter::Nearest) => (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest, 0.0),
+        Some(MinFilter::Linear) => (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest, 0.0),
+        Some(MinFilter::NearestMipmapNearest) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest, 32.0)
+        }
+        Some(MinFilter::LinearMipmapNearest) => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest, 32.0)
+        }
+        Some(MinFilter::NearestMipmapLinear) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear, 32.0)
+        }
+        Some(MinFilter::LinearMipmapLinear) | None => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, 32.0)
+        }
+    };
+
+    wgpu::SamplerDescriptor {
+        label: Some("glTF Sampler"),
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter,
+        min_filter,
+        mipmap_filter,
+        lod_max_clamp,
+        ..Default::default()
+    }
+}
+
+fn to_rgba8(image: &gltf::image::Data) -> Result<Vec<u8>, SceneError> {
+    use gltf::image::Format;
+
+    let (channels, is_16_bit) = match image.format {
+        Format::R8 => (1, false),
+        Format::R8G8 => (2, false),
+        Format::R8G8B8 => (3, false),
+        Format::R8G8B8A8 => (4, false),
+        Format::R16 => (1, true),
+        Format::R16G16 => (2, true),
+        Format::R16G16B16 => (3, true),
+        Format::R16G16B16A16 => (4, true),
+        format => return Err(SceneError::UnsupportedImageFormat(format)),
+    };
+
+    // 16 位的通道按本机字节序保存，按比例缩小到 8 位
+    let samples = if is_16_bit {
+        image
+            .pixels
+            .chunks_exact(2)
+            .map(|c| ((u16::from_ne_bytes([c[0], c[1]]) as u32 * 255 + 32767) / 65535) as u8)
+            .collect()
+    } else {
+        image.pixels.clone()
+    };
+
+    let rgba = match channels {
+        4 => samples,
+        3 => samples
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        2 => samples
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[1], 0, 255])
+            .collect(),
+        _ => samples.iter().flat_map(|&r| [r, r, r, 255]).collect(),
+    };
+    Ok(rgba)
+}
+
+/// 读取图元的顶点和索引；缺少的纹理坐标用 0 填充，缺少的法线和切线由三角形计算，没有索引时按顺序生成
+fn primitive_geometry(
+    mesh: &gltf::Mesh,
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Result<(Vec<ModelVertex>, Vec<u32>), SceneError> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|b| &b.0[..]));
+
+    let positions: Vec<[f32; 3]> = reader
+        .read_positions()
+        .ok_or(SceneError::MissingPositions {
+            mesh: mesh.index(),
+            primitive: primitive.index(),
+        })?
+        .collect();
+    let mut tex_coords = reader.read_tex_coords(0).map(|t| t.into_f32());
+    let has_tex_coords = tex_coords.is_some();
+    let mut normals = reader.read_normals();
+    let has_normals = normals.is_some();
+    let mut tangents = reader.read_tangents();
+    let has_tangents = tangents.is_some();
+
+    let mut vertices: Vec<_> = positions
+        .iter()
+        .map(|&position| ModelVertex {
+            position,
+            tex_coords: tex_coords
+                .as_mut()
+                .and_then(Iterator::next)
+                .unwrap_or_default(),
+            normal: normals
+                .as_mut()
+                .and_then(Iterator::next)
+                .unwrap_or_default(),
+            tangent: tangents
+                .as_mut()
+                .and_then(Iterator::next)
+                .unwrap_or(ModelVertex::DEFAULT_TANGENT),
+        })
+        .collect();
+
+    let mut indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    if !has_normals {
+        compute_normals(&mut vertices, &indices);
+    }
+    // glTF 规定没有提供切线时使用 MikkTSpace 生成
+    if !has_tangents && has_tex_coords {
+        compute_tangents(&mut vertices, &mut indices);
+    }
+
+    Ok((vertices, indices))
+}
+
+/// 每个节点的世界变换，存放在一个使用动态偏移的 uniform 缓冲中
+///
+/// 绘制节点前用 [`NodeTransforms::offset`] 作为 `@group(2)` 的动态偏移
+pub struct NodeTransforms {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    stride: u32,
+    capacity: usize,
+}
+
+impl NodeTransforms {
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Node Transform Bind Group Layout"),
+            entries: &Self::bind_group_layout_entries(),
+        })
+    }
+
+    pub fn bind_group_layout_entries() -> [wgpu::BindGroupLayoutEntry; 1] {
+        [wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Mat4>() as u64),
+            },
+            count: None,
+        }]
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        transforms: &[Mat4],
+    ) -> Self {
+        // 动态偏移必须是 min_uniform_buffer_offset_alignment 的整数倍
+        let alignment = device.limits().min_uniform_buffer_offset_alignment;
+        let stride = (std::mem::size_of::<Mat4>() as u32).next_multiple_of(alignment);
+        let capacity = transforms.len().max(1);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Node Transform Buffer"),
+            size: stride as u64 * capacity as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Node Transform Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<Mat4>() as u64),
+                }),
+            }],
+        });
+
+        let transforms_buffer = Self {
+            buffer,
+            bind_group,
+            stride,
+            capacity,
+        };
+        transforms_buffer.update(queue, transforms);
+        transforms_buffer
+    }
+
+    /// 写入新的变换，数量不能超过创建时的数量
+    pub fn update(&self, queue: &wgpu::Queue, transforms: &[Mat4]) {
+        assert!(transforms.len() <= self.capacity);
+
+        let mut data = vec![0u8; self.stride as usize * transforms.len()];
+        for (chunk, transform) in data.chunks_exact_mut(self.stride as usize).zip(transforms) {
+            chunk[..std::mem::size_of::<Mat4>()]
+                .copy_from_slice(bytemuck::cast_slice(&transform.to_cols_array()));
+        }
+        queue.write_buffer(&self.buffer, 0, &data);
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// 第 `index` 个变换的动态偏移
+    pub fn offset(&self, index: usize) -> wgpu::DynamicOffset {
+        self.stride * index as u32
+    }
+}
+
+/// 在渲染通道上绘制 [`Scene`]
+///
+/// 约定材质位于 `@group(0)`，相机位于 `@group(1)`，节点变换位于 `@group(2)`
+pub trait DrawScene<'a> {
+    /// 使用只有基础颜色纹理的 [`Material`]
+    fn draw_scene(
+        &mut self,
+        scene: &'a Scene,
+        transforms: &'a NodeTransforms,
+        camera_bind_group: &'a wgpu::BindGroup,
+    );
+
+    /// 使用 [`PbrMaterial`]
+    fn draw_scene_pbr(
+        &mut self,
+        scene: &'a Scene,
+        transforms: &'a NodeTransforms,
+        camera_bind_group: &'a wgpu::BindGroup,
+    );
+}
+
+impl<'a, 'b> DrawScene<'b> for wgpu::RenderPass<'a>
+where
+    'b: 'a,
+{
+    fn draw_scene(
+        &mut self,
+        scene: &'b Scene,
+        transforms: &'b NodeTransforms,
+        camera_bind_group: &'b wgpu::BindGroup,
+    ) {
+        draw_nodes(self, scene, transforms, camera_bind_group, |m| {
+            &m.material.bind_group
+        });
+    }
+
+    fn draw_scene_pbr(
+        &mut self,
+        scene: &'b Scene,
+        transforms: &'b NodeTransforms,
+        camera_bind_group: &'b wgpu::BindGroup,
+    ) {
+        draw_nodes(self, scene, transforms, camera_bind_group, |m| {
+            &m.pbr.bind_group
+        });
+    }
+}
+
+fn draw_nodes<'a>(
+    render_pass: &mut wgpu::RenderPass<'_>,
+    scene: &'a Scene,
+    transforms: &'a NodeTransforms,
+    camera_bind_group: &'a wgpu::BindGroup,
+    material_bind_group: impl Fn(&'a SceneMaterial) -> &'a wgpu::BindGroup,
+) {
+    render_pass.set_bind_group(1, camera_bind_group, &[]);
+    for index in scene.mesh_nodes() {
+        let Some(mesh) = scene.nodes[index].mesh else {
+            continue;
+        };
+        render_pass.set_bind_group(2, transforms.bind_group(), &[transforms.offset(index)]);
+        for primitive in &scene.meshes[mesh].primitives {
+            let material = &scene.materials[primitive.material];
+            render_pass.set_bind_group(0, material_bind_group(material), &[]);
+            render_pass.set_vertex_buffer(0, primitive.vertex_buffer.slice(..));
+            render_pass
+                .set_index_buffer(primitive.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..primitive.num_elements, 0, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::headless::{TempDir, test_context};
+
+    /// 一个三角形网格，包含两个图元：第一个使用材质 0，第二个没有材质
+    const TRIANGLE_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "name": "parent", "translation": [1, 0, 0], "children": [1] },
+            { "name": "child", "scale": [2, 2, 2], "mesh": 0 },
+            { "name": "orphan", "mesh": 0 }
+        ],
+        "meshes": [{
+            "name": "triangle",
+            "primitives": [
+                { "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 },
+                { "attributes": { "POSITION": 0 } }
+            ]
+        }],
+        "materials": [{
+            "name": "red",
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [1, 0, 0, 1],
+                "metallicFactor": 0.25,
+                "roughnessFactor": 0.75
+            }
+        }],
+        "buffers": [{ "uri": "triangle.bin", "byteLength": 44 }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+        ],
+        "accessors": [
+            {
+                "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [0, 0, 0], "max": [1, 1, 0]
+            },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ]
+    }"#;
+
+    fn triangle_buffer() -> Vec<u8> {
+        let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let mut data = bytemuck::cast_slice(&positions).to_vec();
+        data.extend_from_slice(bytemuck::cast_slice(&[0u16, 1, 2, 0]));
+        data
+    }
+
+    /// 把 JSON 和二进制缓冲打包成 GLB
+    fn triangle_glb() -> Vec<u8> {
+        let json = TRIANGLE_GLTF.replace(r#""uri": "triangle.bin", "#, "");
+        let mut json = json.into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let bin = triangle_buffer();
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    #[test]
+    fn sampler_follows_gltf() {
+        let gltf = gltf::Gltf::from_slice(
+            br#"{
+                "asset": { "version": "2.0" },
+                "samplers": [
+                    {},
+                    { "magFilter": 9728, "minFilter": 9729, "wrapS": 33071, "wrapT": 33648 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let samplers: Vec<_> = gltf.samplers().map(|s| sampler_descriptor(&s)).collect();
+
+        // 默认的寻址方式是 Repeat
+        assert_eq!(samplers[0].address_mode_u, wgpu::AddressMode::Repeat);
+        assert_eq!(samplers[0].address_mode_v, wgpu::AddressMode::Repeat);
+        assert_eq!(samplers[0].mag_filter, wgpu::FilterMode::Linear);
+        assert_eq!(samplers[0].mipmap_filter, wgpu::FilterMode::Linear);
+
+        assert_eq!(samplers[1].address_mode_u, wgpu::AddressMode::ClampToEdge);
+        assert_eq!(samplers[1].address_mode_v, wgpu::AddressMode::MirrorRepeat);
+        assert_eq!(samplers[1].mag_filter, wgpu::FilterMode::Nearest);
+        assert_eq!(samplers[1].min_filter, wgpu::FilterMode::Linear);
+        assert_eq!(samplers[1].lod_max_clamp, 0.0);
+    }
+
+    #[test]
+    fn sixteen_bit_images_are_converted() {
+        let samples: [u16; 6] = [0, 32768, 65535, 65535, 257, 0];
+        let rgb16 = gltf::image::Data {
+            pixels: bytemuck::cast_slice(&samples).to_vec(),
+            format: gltf::image::Format::R16G16B16,
+            width: 2,
+            height: 1,
+        };
+        assert_eq!(
+            to_rgba8(&rgb16).unwrap(),
+            [0, 128, 255, 255, 255, 1, 0, 255]
+        );
+
+        let float = gltf::image::Data {
+            pixels: vec![0; 12],
+            format: gltf::image::Format::R32G32B32FLOAT,
+            width: 1,
+            height: 1,
+        };
+        assert!(matches!(
+            to_rgba8(&float),
+            Err(SceneError::UnsupportedImageFormat(_))
+        ));
+    }
+
+    fn check_triangle_scene(scene: &Scene) {
+        assert_eq!(scene.roots, vec![0]);
+        assert_eq!(scene.nodes.len(), 3);
+        assert_eq!(scene.meshes[0].primitives.len(), 2);
+        assert_eq!(scene.meshes[0].primitives[0].material, 0);
+        assert_eq!(scene.meshes[0].primitives[1].material, 1);
+        assert_eq!(scene.materials.len(), 2);
+        let factors = scene.materials[0].pbr.factors;
+        assert_eq!(factors.base_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(factors.metallic, 0.25);
+        assert_eq!(factors.roughness, 0.75);
+        assert_eq!(scene.materials[1].pbr.factors, PbrFactors::default());
+
+        // 只有默认场景中的节点会被绘制
+        assert_eq!(scene.mesh_nodes(), vec![1]);
+
+        let transforms = scene.world_transforms();
+        let corner = transforms[1].transform_point3(Vec3::new(1.0, 1.0, 0.0));
+        assert!(corner.abs_diff_eq(Vec3::new(3.0, 2.0, 0.0), 1e-6));
+        assert_eq!(transforms[2], Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn load_gltf_with_external_buffer() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+        let dir = TempDir::new("utils-scene");
+        std::fs::write(dir.join("triangle.gltf"), TRIANGLE_GLTF).unwrap();
+        std::fs::write(dir.join("triangle.bin"), triangle_buffer()).unwrap();
+
+        let mipmaps = MipmapGenerator::new(&ctx.device);
+        let layouts = MaterialLayouts::new(&ctx.device);
+        let scene = Scene::load_gltf(
+            &ctx.device,
+            &ctx.queue,
+            &mipmaps,
+            dir.join("triangle.gltf"),
+            &layouts,
+        )
+        .unwrap();
+
+        check_triangle_scene(&scene);
+    }
+
+    #[test]
+    fn load_glb_with_embedded_buffer() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+
+        let mipmaps = MipmapGenerator::new(&ctx.device);
+        let layouts = MaterialLayouts::new(&ctx.device);
+        let scene = Scene::from_slice(&ctx.device, &ctx.queue, &mipmaps, &triangle_glb(), &layouts)
+            .unwrap();
+
+        check_triangle_scene(&scene);
+    }
+
+    #[test]
+    fn materials_share_uploaded_images() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+        let dir = TempDir::new("utils-scene-textures");
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([0, 0, 255, 255]))
+            .save(dir.join("shared.png"))
+            .unwrap();
+        // 两个纹理引用同一张图片，但采样器不同；第一个材质还把它用作线性的法线贴图
+        std::fs::write(
+            dir.join("shared.gltf"),
+            r#"{
+                "asset": { "version": "2.0" },
+                "images": [{ "uri": "shared.png" }],
+                "samplers": [{}, { "magFilter": 9728 }],
+                "textures": [{ "source": 0, "sampler": 0 }, { "source": 0, "sampler": 1 }],
+                "materials": [
+                    {
+                        "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } },
+                        "normalTexture": { "index": 0 }
+                    },
+                    { "pbrMetallicRoughness": { "baseColorTexture": { "index": 1 } } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let mipmaps = MipmapGenerator::new(&ctx.device);
+        let layouts = MaterialLayouts::new(&ctx.device);
+        let scene = Scene::load_gltf(
+            &ctx.device,
+            &ctx.queue,
+            &mipmaps,
+            dir.join("shared.gltf"),
+            &layouts,
+        )
+        .unwrap();
+
+        let [first, second] = [0, 1].map(|i| &scene.materials[i].material.diffuse_texture);
+        assert_eq!(first.texture, second.texture);
+        assert_ne!(first.sampler, second.sampler);
+    }
+}
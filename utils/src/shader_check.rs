@@ -0,0 +1,881 @@
+//! 不需要 GPU 的着色器检查。
+//!
+//! 用 naga 解析并校验 WGSL，再把入口点的顶点输入、用到的绑定和结构体的成员布局与 Rust 端的
+//! [`wgpu::VertexBufferLayout`]、[`wgpu::BindGroupLayoutEntry`] 和 `#[repr(C)]` 结构体逐项比较，
+//! 让原本要到创建管线时才出现的校验错误在 `cargo test` 中暴露出来。
+
+use std::fmt;
+
+use naga::common::wgsl::TypeContext;
+
+use crate::{
+    hot_reload::{ShaderError, validate_wgsl},
+    preprocess::Preprocessor,
+};
+
+/// 着色器与 Rust 端声明不一致的地方
+#[derive(Debug)]
+pub struct ShaderMismatch {
+    pub shader: String,
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ShaderMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} does not match its pipeline:", self.shader)?;
+        for problem in &self.problems {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderMismatch {}
+
+/// Rust 结构体中一个字段的偏移和大小，由 [`crate::struct_fields!`] 生成
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldLayout {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[doc(hidden)]
+pub fn field_size<T, F>(_: fn(&T) -> &F) -> usize {
+    std::mem::size_of::<F>()
+}
+
+/// 列出结构体字段的 [`FieldLayout`]，供 [`ShaderCheck::struct_layout`] 比较，填充字段不需要列出
+///
+/// ```ignore
+/// check.struct_layout::<CameraUniform>(
+///     "CameraUniform",
+///     utils::struct_fields!(CameraUniform { view_proj, eye, viewport }),
+/// )?;
+/// ```
+#[macro_export]
+macro_rules! struct_fields {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        &[$($crate::shader_check::FieldLayout {
+            name: stringify!($field),
+            offset: ::std::mem::offset_of!($ty, $field),
+            size: $crate::shader_check::field_size(|value: &$ty| &value.$field),
+        }),*]
+    };
+}
+
+/// 解析并校验过的着色器
+pub struct ShaderCheck {
+    name: String,
+    module: naga::Module,
+    info: naga::valid::ModuleInfo,
+}
+
+impl ShaderCheck {
+    /// 预处理并校验 `preprocessor` 中的文件 `name`
+    pub fn new(preprocessor: &Preprocessor, name: &str) -> Result<Self, ShaderError> {
+        let shader = preprocessor.process(name)?;
+        let (module, info) = validate_wgsl(&shader)?;
+        Ok(Self {
+            name: name.to_string(),
+            module,
+            info,
+        })
+    }
+
+    /// 校验没有 `#include` 的着色器
+    pub fn from_wgsl(name: &str, source: &str) -> Result<Self, ShaderError> {
+        Self::new(&Preprocessor::new().with_file(name, source), name)
+    }
+
+    /// 检查存在 `stage` 阶段的入口点 `name`
+    pub fn entry_point(&self, stage: naga::ShaderStage, name: &str) -> Result<(), ShaderMismatch> {
+        self.result(
+            self.find_entry_point(stage, name)
+                .err()
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    /// 检查顶点着色器 `entry` 的每个输入都能在 `buffers` 中找到类型相同的属性
+    pub fn vertex_buffers(
+        &self,
+        entry: &str,
+        buffers: &[wgpu::VertexBufferLayout],
+    ) -> Result<(), ShaderMismatch> {
+        let index = match self.find_entry_point(naga::ShaderStage::Vertex, entry) {
+            Ok(index) => index,
+            Err(problem) => return self.result(vec![problem]),
+        };
+
+        let mut inputs = Vec::new();
+        for argument in &self.module.entry_points[index].function.arguments {
+            match (&argument.binding, &self.module.types[argument.ty].inner) {
+                (Some(naga::Binding::Location { location, .. }), _) => {
+                    inputs.push((*location, argument.ty));
+                }
+                // 结构体参数的成员各自带有 @location
+                (None, naga::TypeInner::Struct { members, .. }) => {
+                    for member in members {
+                        if let Some(naga::Binding::Location { location, .. }) = member.binding {
+                            inputs.push((location, member.ty));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let attributes: Vec<_> = buffers
+            .iter()
+            .flat_map(|buffer| buffer.attributes)
+            .collect();
+        let mut problems = Vec::new();
+        for (location, ty) in inputs {
+            let shader_type = self.module.to_ctx().type_to_string(ty);
+            let matching: Vec<_> = attributes
+                .iter()
+                .filter(|attribute| attribute.shader_location == location)
+                .collect();
+            match matching[..] {
+                [] => problems.push(format!(
+                    "@location({location}) {shader_type} has no vertex attribute"
+                )),
+                [attribute] => {
+                    if vertex_format_type(attribute.format)
+                        != scalar_and_components(&self.module.types[ty].inner)
+                    {
+                        problems.push(format!(
+                            "@location({location}) is {shader_type} but the attribute is {:?}",
+                            attribute.format
+                        ));
+                    }
+                }
+                _ => problems.push(format!(
+                    "@location({location}) is used by {} vertex attributes",
+                    matching.len()
+                )),
+            }
+        }
+        self.result(problems)
+    }
+
+    /// 检查 `entries` 中的入口点用到的每个绑定，`layouts[i]` 为 `@group(i)` 的布局项
+    pub fn bind_groups(
+        &self,
+        entries: &[&str],
+        layouts: &[&[wgpu::BindGroupLayoutEntry]],
+    ) -> Result<(), ShaderMismatch> {
+        let mut problems = Vec::new();
+        let mut used = Vec::new();
+        for &entry in entries {
+            let Some(index) = self
+                .module
+                .entry_points
+                .iter()
+                .position(|point| point.name == entry)
+            else {
+                problems.push(format!("missing entry point {entry}"));
+                continue;
+            };
+            let stage = match self.module.entry_points[index].stage {
+                naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                _ => wgpu::ShaderStages::COMPUTE,
+            };
+            let function = self.info.get_entry_point(index);
+            for (handle, _) in self.module.global_variables.iter() {
+                if !function[handle].is_empty() {
+                    used.push((handle, stage));
+                }
+            }
+        }
+
+        for (handle, variable) in self.module.global_variables.iter() {
+            let Some(binding) = &variable.binding else {
+                continue;
+            };
+            let stages = used
+                .iter()
+                .filter(|&&(used, _)| used == handle)
+                .fold(wgpu::ShaderStages::NONE, |stages, &(_, stage)| {
+                    stages | stage
+                });
+            if stages.is_empty() {
+                continue;
+            }
+
+            let name = format!(
+                "@group({}) @binding({}) {}",
+                binding.group,
+                binding.binding,
+                variable.name.as_deref().unwrap_or("_")
+            );
+            let Some(entry) = layouts
+                .get(binding.group as usize)
+                .and_then(|layout| layout.iter().find(|entry| entry.binding == binding.binding))
+            else {
+                problems.push(format!("{name} has no bind group layout entry"));
+                continue;
+            };
+            if !entry.visibility.contains(stages) {
+                problems.push(format!(
+                    "{name} is used in {stages:?} but only visible in {:?}",
+                    entry.visibility
+                ));
+            }
+            if let Err(problem) = self.check_binding_type(variable, &entry.ty) {
+                problems.push(format!("{name}: {problem}"));
+            }
+        }
+        problems.extend(self.check_sampled_textures(entries, layouts));
+        self.result(problems)
+    }
+
+    /// 用过滤采样器采样的纹理必须是可过滤的，这是纹理与采样器两个绑定之间的约束
+    fn check_sampled_textures(
+        &self,
+        entries: &[&str],
+        layouts: &[&[wgpu::BindGroupLayoutEntry]],
+    ) -> Vec<String> {
+        let layout_entry = |handle: naga::Handle<naga::GlobalVariable>| {
+            let binding = self.module.global_variables[handle].binding.as_ref()?;
+            layouts
+                .get(binding.group as usize)?
+                .iter()
+                .find(|entry| entry.binding == binding.binding)
+        };
+        let describe = |handle: naga::Handle<naga::GlobalVariable>| {
+            let variable = &self.module.global_variables[handle];
+            let binding = variable
+                .binding
+                .as_ref()
+                .expect("sampled globals are bound");
+            format!(
+                "@group({}) @binding({}) {}",
+                binding.group,
+                binding.binding,
+                variable.name.as_deref().unwrap_or("_")
+            )
+        };
+
+        // 入口点和辅助函数中直接采样全局纹理的表达式；通过函数参数传入的纹理不检查
+        let functions = self
+            .module
+            .entry_points
+            .iter()
+            .filter(|point| entries.contains(&point.name.as_str()))
+            .map(|point| &point.function)
+            .chain(self.module.functions.iter().map(|(_, function)| function));
+        let mut pairs = Vec::new();
+        for function in functions {
+            for (_, expression) in function.expressions.iter() {
+                let naga::Expression::ImageSample { image, sampler, .. } = *expression else {
+                    continue;
+                };
+                if let (
+                    naga::Expression::GlobalVariable(image),
+                    naga::Expression::GlobalVariable(sampler),
+                ) = (&function.expressions[image], &function.expressions[sampler])
+                    && !pairs.contains(&(*image, *sampler))
+                {
+                    pairs.push((*image, *sampler));
+                }
+            }
+        }
+
+        let mut problems = Vec::new();
+        for (image, sampler) in pairs {
+            let (Some(texture), Some(filtering)) = (layout_entry(image), layout_entry(sampler))
+            else {
+                continue;
+            };
+            if matches!(
+                texture.ty,
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    ..
+                }
+            ) && filtering.ty == wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+            {
+                problems.push(format!(
+                    "{} is not filterable but is sampled with the filtering sampler {}",
+                    describe(image),
+                    describe(sampler)
+                ));
+            }
+        }
+        problems
+    }
+
+    /// 检查 WGSL 结构体 `name` 的每个成员与 `fields` 中同名字段的偏移和大小一致，
+    /// 结构体大小与 `T` 一致
+    ///
+    /// 成员的偏移由 naga 按 WGSL 的对齐规则计算，例如 `vec2f` 之后的 `vec3f` 对齐到 16 字节；
+    /// 用在 uniform 中的结构体是否满足 uniform 地址空间的额外规则（数组步长是 16 的倍数等）由 naga 的校验负责
+    pub fn struct_layout<T>(
+        &self,
+        name: &str,
+        fields: &[FieldLayout],
+    ) -> Result<(), ShaderMismatch> {
+        let Some((_, ty)) = self
+            .module
+            .types
+            .iter()
+            .find(|(_, ty)| ty.name.as_deref() == Some(name))
+        else {
+            return self.result(vec![format!("missing struct {name}")]);
+        };
+        let naga::TypeInner::Struct { members, span } = &ty.inner else {
+            return self.result(vec![format!("{name} is not a struct")]);
+        };
+
+        let mut problems = Vec::new();
+        let size = std::mem::size_of::<T>();
+        if *span as usize != size {
+            problems.push(format!(
+                "{name} is {span} bytes in WGSL but {size} bytes in Rust"
+            ));
+        }
+        for member in members {
+            let member_name = member.name.as_deref().unwrap_or("_");
+            let Some(field) = fields.iter().find(|field| field.name == member_name) else {
+                problems.push(format!("{name}.{member_name} has no Rust field"));
+                continue;
+            };
+            if member.offset as usize != field.offset {
+                problems.push(format!(
+                    "{name}.{member_name} is at offset {} in WGSL but {} in Rust",
+                    member.offset, field.offset
+                ));
+            }
+            let member_size = self.module.types[member.ty]
+                .inner
+                .size(self.module.to_ctx()) as usize;
+            if member_size != field.size {
+                problems.push(format!(
+                    "{name}.{member_name} is {member_size} bytes in WGSL but {} bytes in Rust",
+                    field.size
+                ));
+            }
+        }
+        for field in fields {
+            if !members
+                .iter()
+                .any(|member| member.name.as_deref() == Some(field.name))
+            {
+                problems.push(format!("{name} has no member {}", field.name));
+            }
+        }
+        self.result(problems)
+    }
+
+    fn find_entry_point(&self, stage: naga::ShaderStage, name: &str) -> Result<usize, String> {
+        self.module
+            .entry_points
+            .iter()
+            .position(|point| point.stage == stage && point.name == name)
+            .ok_or_else(|| format!("missing {stage:?} entry point {name}"))
+    }
+
+    fn check_binding_type(
+        &self,
+        variable: &naga::GlobalVariable,
+        ty: &wgpu::BindingType,
+    ) -> Result<(), String> {
+        use naga::{AddressSpace, ImageClass, ImageDimension, ScalarKind, TypeInner};
+        use wgpu::{BindingType, BufferBindingType, TextureSampleType, TextureViewDimension};
+
+        let inner = &self.module.types[variable.ty].inner;
+        let shader_type = self.module.to_ctx().type_to_string(variable.ty);
+        let mismatch = || Err(format!("{shader_type} cannot be bound as {ty:?}"));
+        match (variable.space, inner, ty) {
+            (
+                AddressSpace::Uniform,
+                _,
+                BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    min_binding_size,
+                    ..
+                },
+            ) => {
+                let size = inner.size(self.module.to_ctx()) as u64;
+                match min_binding_size {
+                    Some(min) if min.get() < size => Err(format!(
+                        "{shader_type} needs {size} bytes but min_binding_size is {min}"
+                    )),
+                    _ => Ok(()),
+                }
+            }
+            (
+                AddressSpace::Storage { access },
+                _,
+                BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only },
+                    ..
+                },
+            ) => {
+                // 只读和可写的存储缓冲区不能混用
+                if *read_only != access.contains(naga::StorageAccess::STORE) {
+                    Ok(())
+                } else {
+                    mismatch()
+                }
+            }
+            (
+                AddressSpace::Handle,
+                TypeInner::Image {
+                    dim,
+                    arrayed,
+                    class,
+                },
+                BindingType::Texture {
+                    sample_type,
+                    view_dimension,
+                    multisampled,
+                },
+            ) => {
+                let dimension = match (dim, arrayed) {
+                    (ImageDimension::D1, _) => TextureViewDimension::D1,
+                    (ImageDimension::D2, false) => TextureViewDimension::D2,
+                    (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+                    (ImageDimension::D3, _) => TextureViewDimension::D3,
+                    (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+                    (ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+                };
+                let class_matches = match (class, sample_type) {
+                    (ImageClass::Sampled { kind, multi }, sample_type) => {
+                        *multi == *multisampled
+                            && matches!(
+                                (kind, sample_type),
+                                (ScalarKind::Float, TextureSampleType::Float { .. })
+                                    | (ScalarKind::Sint, TextureSampleType::Sint)
+                                    | (ScalarKind::Uint, TextureSampleType::Uint)
+                            )
+                    }
+                    (ImageClass::Depth { multi }, TextureSampleType::Depth) => {
+                        *multi == *multisampled
+                    }
+                    _ => false,
+                };
+                if dimension == *view_dimension && class_matches {
+                    Ok(())
+                } else {
+                    mismatch()
+                }
+            }
+            (
+                AddressSpace::Handle,
+                TypeInner::Sampler { comparison },
+                BindingType::Sampler(sampler),
+            ) => {
+                if *comparison == (*sampler == wgpu::SamplerBindingType::Comparison) {
+                    Ok(())
+                } else {
+                    mismatch()
+                }
+            }
+            (AddressSpace::Handle, TypeInner::Image { .. }, BindingType::StorageTexture { .. }) => {
+                Ok(())
+            }
+            (AddressSpace::Uniform | AddressSpace::Storage { .. }, _, _)
+            | (AddressSpace::Handle, _, _) => mismatch(),
+            _ => Ok(()),
+        }
+    }
+
+    fn result(&self, problems: Vec<String>) -> Result<(), ShaderMismatch> {
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ShaderMismatch {
+                shader: self.name.clone(),
+                problems,
+            })
+        }
+    }
+}
+
+/// 顶点格式在着色器中对应的标量类型和分量个数
+fn vertex_format_type(format: wgpu::VertexFormat) -> (naga::ScalarKind, u32) {
+    use naga::ScalarKind::{Float, Sint, Uint};
+    use wgpu::VertexFormat as F;
+
+    match format {
+        F::Uint8 | F::Uint16 | F::Uint32 => (Uint, 1),
+        F::Uint8x2 | F::Uint16x2 | F::Uint32x2 => (Uint, 2),
+        F::Uint32x3 => (Uint, 3),
+        F::Uint8x4 | F::Uint16x4 | F::Uint32x4 => (Uint, 4),
+        F::Sint8 | F::Sint16 | F::Sint32 => (Sint, 1),
+        F::Sint8x2 | F::Sint16x2 | F::Sint32x2 => (Sint, 2),
+        F::Sint32x3 => (Sint, 3),
+        F::Sint8x4 | F::Sint16x4 | F::Sint32x4 => (Sint, 4),
+        F::Unorm8 | F::Snorm8 | F::Unorm16 | F::Snorm16 | F::Float16 | F::Float32 | F::Float64 => {
+            (Float, 1)
+        }
+        F::Unorm8x2
+        | F::Snorm8x2
+        | F::Unorm16x2
+        | F::Snorm16x2
+        | F::Float16x2
+        | F::Float32x2
+        | F::Float64x2 => (Float, 2),
+        F::Float32x3 | F::Float64x3 => (Float, 3),
+        F::Unorm8x4
+        | F::Snorm8x4
+        | F::Unorm16x4
+        | F::Snorm16x4
+        | F::Float16x4
+        | F::Float32x4
+        | F::Float64x4
+        | F::Unorm10_10_10_2
+        | F::Unorm8x4Bgra => (Float, 4),
+    }
+}
+
+fn scalar_and_components(inner: &naga::TypeInner) -> (naga::ScalarKind, u32) {
+    match *inner {
+        naga::TypeInner::Scalar(scalar) => (scalar.kind, 1),
+        naga::TypeInner::Vector { size, scalar } => (scalar.kind, size as u32),
+        // 顶点输入只能是标量或向量，其他类型一定不匹配
+        _ => (naga::ScalarKind::Bool, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    const SHADER: &str = "
+struct Camera { view_proj: mat4x4f }
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(1) @binding(0) var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1) var s_diffuse: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) tex_coord: vec2f,
+}
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) tex_coord: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexInput, @location(5) tint: vec4f) -> VertexOutput {
+    return VertexOutput(camera.view_proj * vec4f(in.position, 1.0) * tint.a, in.tex_coord);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureSample(t_diffuse, s_diffuse, in.tex_coord);
+}
+";
+
+    fn camera_entry(visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+
+    const TEXTURE_ENTRIES: [wgpu::BindGroupLayoutEntry; 2] = [
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        },
+    ];
+
+    #[test]
+    fn matching_layouts_pass() {
+        let check = ShaderCheck::from_wgsl("shader.wgsl", SHADER).unwrap();
+        check
+            .entry_point(naga::ShaderStage::Fragment, "fs_main")
+            .unwrap();
+        check
+            .vertex_buffers(
+                "vs_main",
+                &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: 20,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: 4,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &wgpu::vertex_attr_array![5 => Unorm8x4],
+                    },
+                ],
+            )
+            .unwrap();
+        check
+            .bind_groups(
+                &["vs_main", "fs_main"],
+                &[
+                    &[camera_entry(wgpu::ShaderStages::VERTEX)],
+                    &TEXTURE_ENTRIES,
+                ],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn mismatches_are_reported() {
+        let check = ShaderCheck::from_wgsl("shader.wgsl", SHADER).unwrap();
+        assert!(
+            check
+                .entry_point(naga::ShaderStage::Vertex, "fs_main")
+                .is_err()
+        );
+
+        let error = check
+            .vertex_buffers(
+                "vs_main",
+                &[wgpu::VertexBufferLayout {
+                    array_stride: 20,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Uint32x2],
+                }],
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.problems,
+            [
+                "@location(1) is vec2<f32> but the attribute is Uint32x2",
+                "@location(5) vec4<f32> has no vertex attribute",
+            ]
+        );
+
+        let error = check
+            .bind_groups(
+                &["vs_main", "fs_main"],
+                &[
+                    &[camera_entry(wgpu::ShaderStages::FRAGMENT)],
+                    &TEXTURE_ENTRIES[..1],
+                ],
+            )
+            .unwrap_err();
+        assert_eq!(error.problems.len(), 2, "{error}");
+        assert!(error.problems[0].contains("only visible in"), "{error}");
+        assert!(
+            error.problems[1].starts_with("@group(1) @binding(1) s_diffuse has no"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn unfilterable_texture_needs_a_non_filtering_sampler() {
+        let check = ShaderCheck::from_wgsl("shader.wgsl", SHADER).unwrap();
+        let camera = [camera_entry(wgpu::ShaderStages::VERTEX)];
+        let texture = |filterable, sampler| {
+            let [mut texture_entry, mut sampler_entry] = TEXTURE_ENTRIES;
+            texture_entry.ty = wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable },
+            };
+            sampler_entry.ty = wgpu::BindingType::Sampler(sampler);
+            [texture_entry, sampler_entry]
+        };
+
+        let unfilterable = texture(false, wgpu::SamplerBindingType::NonFiltering);
+        check
+            .bind_groups(&["vs_main", "fs_main"], &[&camera, &unfilterable])
+            .unwrap();
+
+        // 单独看每个绑定都没有问题，组合在一起时不能过滤
+        let mismatched = texture(false, wgpu::SamplerBindingType::Filtering);
+        let error = check
+            .bind_groups(&["vs_main", "fs_main"], &[&camera, &mismatched])
+            .unwrap_err();
+        assert_eq!(
+            error.problems,
+            [
+                "@group(1) @binding(0) t_diffuse is not filterable but is sampled with \
+                 the filtering sampler @group(1) @binding(1) s_diffuse"
+            ]
+        );
+    }
+
+    #[test]
+    fn struct_layout_follows_wgsl_alignment() {
+        let check = ShaderCheck::from_wgsl(
+            "params.wgsl",
+            "
+struct Params {
+    scale: vec2f,
+    color: vec3f,
+    weights: array<vec4f, 2>,
+}
+@group(0) @binding(0) var<uniform> params: Params;
+
+@compute @workgroup_size(1)
+fn main() {
+    _ = params.scale;
+}
+",
+        )
+        .unwrap();
+
+        // `vec3f` 按 16 字节对齐，`color` 前面需要 8 字节的填充
+        #[repr(C)]
+        struct Padded {
+            scale: [f32; 2],
+            _padding: [f32; 2],
+            color: [f32; 3],
+            _padding2: f32,
+            weights: [[f32; 4]; 2],
+        }
+        check
+            .struct_layout::<Padded>(
+                "Params",
+                struct_fields!(Padded {
+                    scale,
+                    color,
+                    weights
+                }),
+            )
+            .unwrap();
+
+        // 直接照抄 WGSL 的字段：`color` 的偏移不对，数组步长只有 4 字节
+        #[repr(C)]
+        struct Naive {
+            scale: [f32; 2],
+            color: [f32; 3],
+            weights: [f32; 2],
+        }
+        let error = check
+            .struct_layout::<Naive>(
+                "Params",
+                struct_fields!(Naive {
+                    scale,
+                    color,
+                    weights
+                }),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.problems,
+            [
+                "Params is 64 bytes in WGSL but 28 bytes in Rust",
+                "Params.color is at offset 16 in WGSL but 8 in Rust",
+                "Params.weights is at offset 32 in WGSL but 20 in Rust",
+                "Params.weights is 32 bytes in WGSL but 8 bytes in Rust",
+            ]
+        );
+
+        let error = check
+            .struct_layout::<Padded>("Params", struct_fields!(Padded { scale, color }))
+            .unwrap_err();
+        assert_eq!(error.problems, ["Params.weights has no Rust field"]);
+    }
+
+    fn wgsl_files(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                // 跳过构建目录和 `.git` 等隐藏目录
+                let name = path.file_name().unwrap().to_string_lossy();
+                if name != "target" && !name.starts_with('.') {
+                    wgsl_files(&path, files);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "wgsl") {
+                files.push(path);
+            }
+        }
+    }
+
+    /// 片段入口点不是 `fs_main` 的着色器，按相对于工作区的路径区分同名文件，
+    /// 空列表表示只有顶点阶段
+    const FRAGMENT_ENTRY_POINTS: &[(&str, &[&str])] = &[
+        ("beginner-03/src/shadow.wgsl", &[]),
+        (
+            "utils/src/post/bloom.wgsl",
+            &[
+                "fs_prefilter",
+                "fs_blur_horizontal",
+                "fs_blur_vertical",
+                "fs_composite",
+            ],
+        ),
+    ];
+
+    /// 工作区中的每个着色器都能通过校验，有 `vs_main`，片段入口点是 `fs_main`
+    /// 或者与 [`FRAGMENT_ENTRY_POINTS`] 中列出的一致；
+    /// 只被其他文件引入的文件不单独校验，它们依赖引入者声明的变量
+    #[test]
+    fn every_workspace_shader_is_valid() {
+        let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let mut files = Vec::new();
+        wgsl_files(workspace, &mut files);
+        assert!(files.len() > 10);
+
+        // `common.wgsl` 通过 `Preprocessor::with_common` 注册，由引入它的着色器校验
+        let common = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/common.wgsl");
+        let included: Vec<PathBuf> = std::iter::once(common)
+            .chain(files.iter().flat_map(|path| {
+                let source = std::fs::read_to_string(path).unwrap();
+                let dir = path.parent().unwrap().to_path_buf();
+                source
+                    .lines()
+                    .filter_map(|line| line.trim().strip_prefix("#include"))
+                    .map(|include| dir.join(include.trim().trim_matches('"')))
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+
+        for path in files.iter().filter(|path| !included.contains(path)) {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            let relative = path
+                .strip_prefix(workspace)
+                .unwrap()
+                .components()
+                .map(|component| component.as_os_str().to_str().unwrap())
+                .collect::<Vec<_>>()
+                .join("/");
+            let preprocessor = Preprocessor::new()
+                .with_common()
+                .with_root(path.parent().unwrap());
+            let check = ShaderCheck::new(&preprocessor, name)
+                .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            check
+                .entry_point(naga::ShaderStage::Vertex, "vs_main")
+                .unwrap();
+
+            let expected = FRAGMENT_ENTRY_POINTS
+                .iter()
+                .find(|(file, _)| *file == relative)
+                .map_or(&["fs_main"][..], |(_, entries)| entries);
+            let fragment: Vec<&str> = check
+                .module
+                .entry_points
+                .iter()
+                .filter(|entry| entry.stage == naga::ShaderStage::Fragment)
+                .map(|entry| entry.name.as_str())
+                .collect();
+            assert_eq!(fragment, expected, "{}", path.display());
+        }
+    }
+}
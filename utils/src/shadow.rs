@@ -0,0 +1,211 @@
+use glam::{Mat4, Vec3};
+
+/// 阴影贴图：每个光源视角占用深度纹理数组中的一层
+pub struct ShadowMap {
+    pub texture: wgpu::Texture,
+    /// 整个数组的视图，在着色器中作为 `texture_depth_2d_array` 采样
+    pub view: wgpu::TextureView,
+    /// 每一层单独的视图，作为阴影通道的深度附件
+    pub layer_views: Vec<wgpu::TextureView>,
+    /// 比较采样器，开启线性过滤后每次采样会对相邻的 4 个像素做比较并插值
+    pub sampler: wgpu::Sampler,
+    pub size: u32,
+}
+
+impl ShadowMap {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    pub fn new(device: &wgpu::Device, size: u32, layers: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Map"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Shadow Map View"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        let layer_views = (0..layers)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Map Layer View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            layer_views,
+            sampler,
+            size,
+        }
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.layer_views.len() as u32
+    }
+}
+
+/// 级联的分割距离（视图空间中的深度），返回每一级的远端，最后一个等于 `far`
+///
+/// 在均匀分割和对数分割之间按 `lambda` 插值，`lambda` 为 1 时完全使用对数分割
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            let log = near * (far / near).powf(t);
+            let uniform = near + (far - near) * t;
+            lambda * log + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// 相机视锥体在视图空间深度 `near` 到 `far` 之间的部分的 8 个角，位于世界空间
+///
+/// 只依赖投影矩阵的逆，因此透视、正交以及无限远的投影都适用
+pub fn frustum_slice_corners(view: Mat4, proj: Mat4, near: f32, far: f32) -> [Vec3; 8] {
+    let inv_proj = proj.inverse();
+    let inv_view = view.inverse();
+    let mut corners = [Vec3::ZERO; 8];
+    for (i, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .into_iter()
+        .enumerate()
+    {
+        // 同一条视线上的两个点，避开可能位于无穷远处的远平面
+        let a = inv_proj.project_point3(Vec3::new(x, y, 0.25));
+        let b = inv_proj.project_point3(Vec3::new(x, y, 0.75));
+        let at_depth = |depth: f32| a + (b - a) * ((-depth - a.z) / (b.z - a.z));
+        corners[i] = inv_view.transform_point3(at_depth(near));
+        corners[i + 4] = inv_view.transform_point3(at_depth(far));
+    }
+    corners
+}
+
+/// 方向光的一级级联：用包围球包住视锥体切片，相机旋转时阴影贴图的尺寸不变，
+/// 再把中心对齐到阴影贴图的像素，相机移动时阴影边缘不会闪烁
+///
+/// `caster_distance` 把近平面向光源方向延伸，使视锥体之外的物体也能投射阴影
+pub fn directional_light_matrix(
+    direction: Vec3,
+    corners: &[Vec3; 8],
+    resolution: u32,
+    caster_distance: f32,
+) -> Mat4 {
+    let direction = direction.normalize();
+    let up = if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+
+    let center = corners.iter().sum::<Vec3>() / 8.0;
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+    // 半径取整，避免浮点误差使阴影贴图每帧缩放
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let rotation = Mat4::look_to_rh(Vec3::ZERO, direction, up);
+    let texel = 2.0 * radius / resolution as f32;
+    let snapped = (rotation.transform_point3(center) / texel).floor() * texel;
+    let center = rotation.inverse().transform_point3(snapped);
+
+    let eye = center - direction * (radius + caster_distance);
+    let view = Mat4::look_to_rh(eye, direction, up);
+    let proj = Mat4::orthographic_rh(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        0.0,
+        2.0 * radius + caster_distance,
+    );
+    proj * view
+}
+
+/// 聚光灯的透视矩阵，视角为外圆锥半角 `angle` 的两倍
+pub fn spot_light_matrix(position: Vec3, direction: Vec3, angle: f32, near: f32, far: f32) -> Mat4 {
+    let direction = direction.normalize();
+    let up = if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let view = Mat4::look_to_rh(position, direction, up);
+    let proj = Mat4::perspective_rh(2.0 * angle, 1.0, near, far);
+    proj * view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cascade_splits_end_at_far_plane() {
+        let splits = cascade_splits(0.1, 20.0, 3, 0.5);
+
+        assert_eq!(splits.len(), 3);
+        assert!(splits.windows(2).all(|w| w[0] < w[1]));
+        assert!((splits[2] - 20.0).abs() < 1e-4);
+        // 对数分割使近处的级联更小
+        assert!(splits[0] < 20.0 / 3.0);
+    }
+
+    #[test]
+    fn cascade_covers_frustum_slice() {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 1.0, 2.0), Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_infinite_reverse_rh(45f32.to_radians(), 1.5, 0.1);
+        let corners = frustum_slice_corners(view, proj, 0.5, 4.0);
+
+        for (corner, depth) in corners.iter().zip([0.5; 4].into_iter().chain([4.0; 4])) {
+            let view_depth = -view.transform_point3(*corner).z;
+            assert!((view_depth - depth).abs() < 1e-3);
+        }
+
+        let matrix = directional_light_matrix(Vec3::new(0.3, -1.0, -0.5), &corners, 1024, 5.0);
+        for corner in corners {
+            let ndc = matrix.project_point3(corner);
+            assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0, "{ndc}");
+            assert!((0.0..=1.0).contains(&ndc.z), "{ndc}");
+        }
+    }
+
+    #[test]
+    fn spot_light_looks_along_direction() {
+        let matrix = spot_light_matrix(Vec3::Y, Vec3::NEG_Y, 0.5, 0.1, 10.0);
+        let ndc = matrix.project_point3(Vec3::new(0.0, -1.0, 0.0));
+
+        assert!(ndc.x.abs() < 1e-5 && ndc.y.abs() < 1e-5);
+        assert!((0.0..1.0).contains(&ndc.z));
+    }
+}
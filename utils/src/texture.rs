@@ -0,0 +1,424 @@
+use std::{fmt, path::Path};
+
+use image::GenericImageView;
+
+use crate::mipmap::MipmapGenerator;
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    /// 宽或高为 0，wgpu 无法创建这样的纹理
+    EmptyImage {
+        width: u32,
+        height: u32,
+    },
+    /// 原始 RGBA 数据的长度与给定尺寸不一致
+    InvalidSize {
+        expected: usize,
+        actual: usize,
+    },
+    /// 颜色查找表的图片不是 `size^2 x size` 的横条
+    InvalidLutSize {
+        width: u32,
+        height: u32,
+    },
+    /// 立方体贴图的六个面不是同样大小的正方形
+    InvalidCubeFace {
+        face: usize,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io(e) => write!(f, "failed to read texture: {e}"),
+            TextureError::Image(e) => write!(f, "failed to decode texture: {e}"),
+            TextureError::EmptyImage { width, height } => {
+                write!(
+                    f,
+                    "invalid texture size {width}x{height}: both sides must be non-zero"
+                )
+            }
+            TextureError::InvalidSize { expected, actual } => write!(
+                f,
+                "invalid rgba data: expected {expected} bytes, got {actual}"
+            ),
+            TextureError::InvalidLutSize { width, height } => write!(
+                f,
+                "invalid color lut: expected a size^2 x size strip, got {width}x{height}"
+            ),
+            TextureError::InvalidCubeFace {
+                face,
+                width,
+                height,
+            } => write!(
+                f,
+                "invalid cube face {face}: expected a square matching the first face, got {width}x{height}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<std::io::Error> for TextureError {
+    fn from(e: std::io::Error) -> Self {
+        TextureError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(e: image::ImageError) -> Self {
+        TextureError::Image(e)
+    }
+}
+
+/// 创建纹理时的可选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextureOptions<'a> {
+    /// 设置后用它在 GPU 上生成完整的 mip 链，并让采样器在 mip 级别之间线性过滤
+    pub mipmaps: Option<&'a MipmapGenerator>,
+    /// 使用 `Rgba8Unorm` 而不是 `Rgba8UnormSrgb`，用于法线、金属度-粗糙度等非颜色数据
+    pub linear: bool,
+    /// 设置后用它创建采样器，否则使用 `ClampToEdge` 寻址，过滤方式由 `mipmaps` 决定
+    pub sampler: Option<&'a wgpu::SamplerDescriptor<'a>>,
+}
+
+impl<'a> TextureOptions<'a> {
+    pub fn mipmapped(mipmaps: &'a MipmapGenerator) -> Self {
+        Self {
+            mipmaps: Some(mipmaps),
+            linear: false,
+            sampler: None,
+        }
+    }
+
+    pub fn linear_mipmapped(mipmaps: &'a MipmapGenerator) -> Self {
+        Self {
+            mipmaps: Some(mipmaps),
+            linear: true,
+            sampler: None,
+        }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        if self.linear {
+            wgpu::TextureFormat::Rgba8Unorm
+        } else {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub size: wgpu::Extent3d,
+    pub format: wgpu::TextureFormat,
+}
+
+impl Texture {
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    /// 创建与表面配置同样大小的深度纹理，窗口尺寸变化后需要重新创建
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        label: Option<&str>,
+    ) -> Self {
+        Self::create_multisampled_depth_texture(device, config, 1, label)
+    }
+
+    /// 与 [`Texture::create_depth_texture`] 相同，采样数需要与颜色附件一致
+    ///
+    /// 多重采样的深度纹理只能作为附件：GL 后端上可绑定的多重采样深度纹理会使帧缓冲不完整
+    pub fn create_multisampled_depth_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+        label: Option<&str>,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.width.max(1),
+            height: config.height.max(1),
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            usage: if sample_count > 1 {
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+            } else {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+            },
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            size,
+            format: Self::DEPTH_FORMAT,
+        }
+    }
+
+    /// 创建与表面配置同样大小、可以渲染也可以采样的颜色纹理，用于离屏渲染和后处理，
+    /// 窗口尺寸变化后需要重新创建
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.width.max(1),
+            height: config.height.max(1),
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            size,
+            format,
+        }
+    }
+
+    /// 从编码后的图片数据（PNG、JPEG）创建纹理
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: Option<&str>,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let img = image::load_from_memory(bytes)?;
+        Self::from_image(device, queue, &img, label, options)
+    }
+
+    pub fn from_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(device, queue, &bytes, path.to_str(), options)
+    }
+
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let (width, height) = img.dimensions();
+        Self::from_rgba(
+            device,
+            queue,
+            &img.to_rgba8(),
+            width,
+            height,
+            label,
+            options,
+        )
+    }
+
+    /// 从按行紧密排列的 RGBA8 像素创建纹理，格式由 [`TextureOptions::linear`] 决定
+    pub fn from_rgba(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        label: Option<&str>,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        if width == 0 || height == 0 {
+            return Err(TextureError::EmptyImage { width, height });
+        }
+
+        let expected = 4 * width as usize * height as usize;
+        if rgba.len() != expected {
+            return Err(TextureError::InvalidSize {
+                expected,
+                actual: rgba.len(),
+            });
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let format = options.format();
+
+        let (mip_level_count, usage) = if options.mipmaps.is_some() {
+            (
+                size.max_mips(wgpu::TextureDimension::D2),
+                wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_DST
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        } else {
+            (
+                1,
+                wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            )
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        if let Some(mipmaps) = options.mipmaps {
+            mipmaps.generate(device, queue, &texture);
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let (min_filter, mipmap_filter) = if options.mipmaps.is_some() {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear)
+        } else {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest)
+        };
+
+        let sampler = device.create_sampler(options.sampler.unwrap_or(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter,
+            mipmap_filter,
+            ..Default::default()
+        }));
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+            size,
+            format,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::test_context;
+
+    #[test]
+    fn from_rgba_rejects_empty_images() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+
+        let mipmaps = MipmapGenerator::new(&ctx.device);
+        for (width, height) in [(0, 4), (4, 0), (0, 0)] {
+            let result = Texture::from_rgba(
+                &ctx.device,
+                &ctx.queue,
+                &[],
+                width,
+                height,
+                None,
+                TextureOptions::mipmapped(&mipmaps),
+            );
+            assert!(matches!(
+                result,
+                Err(TextureError::EmptyImage { width: w, height: h }) if (w, h) == (width, height)
+            ));
+        }
+
+        let result = Texture::from_rgba(
+            &ctx.device,
+            &ctx.queue,
+            &[0; 12],
+            2,
+            2,
+            None,
+            TextureOptions::default(),
+        );
+        assert!(matches!(
+            result,
+            Err(TextureError::InvalidSize {
+                expected: 16,
+                actual: 12
+            })
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+//! 持有缓冲、绑定组和布局的 uniform 缓冲，替代手写的 `queue.write_buffer`。
+//!
+//! ```ignore
+//! let camera = UniformBuffer::new(&device, "Camera", ShaderStages::VERTEX, CameraUniform::new());
+//! // 每帧调用，值没有变化时不会写入缓冲
+//! camera.update(&queue, &uniform);
+//! render_pass.set_bind_group(0, camera.bind_group(), &[]);
+//! ```
+
+use std::num::NonZeroU64;
+
+use bytemuck::Pod;
+use wgpu::util::DeviceExt;
+
+/// WGSL uniform 地址空间中结构体的大小必须是 16 的倍数
+pub const UNIFORM_ALIGNMENT: usize = 16;
+
+/// 类型为 `T` 的 uniform 缓冲，绑定组只包含 `@binding(0)`
+///
+/// 编译期只检查 `T` 的大小：必须非零且是 [`UNIFORM_ALIGNMENT`] 的倍数。
+/// `Pod` 保证结构体没有隐式的填充字节，WGSL 对齐要求的填充需要写成字段；
+/// [`UniformBuffer::new`] 不检查字段的偏移，字段的对齐只由测试中的
+/// [`crate::shader_check::ShaderCheck::struct_layout`] 与着色器比较
+pub struct UniformBuffer<T> {
+    value: T,
+    /// `value` 修改后还没有写入缓冲
+    dirty: bool,
+    buffer: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl<T: Pod> UniformBuffer<T> {
+    /// 只检查总大小，字段的偏移见 [`UniformBuffer`] 的说明
+    const SIZE: u64 = {
+        let size = std::mem::size_of::<T>();
+        assert!(size > 0, "uniform type must not be zero-sized");
+        assert!(
+            size.is_multiple_of(UNIFORM_ALIGNMENT),
+            "uniform type size must be a multiple of 16 bytes; add explicit padding fields"
+        );
+        size as u64
+    };
+
+    /// 超过设备的 `max_uniform_buffer_binding_size` 时 panic
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        visibility: wgpu::ShaderStages,
+        value: T,
+    ) -> Self {
+        let limit = device.limits().max_uniform_buffer_binding_size as u64;
+        assert!(
+            Self::SIZE <= limit,
+            "{label}: uniform type is {} bytes but the device allows at most {limit}",
+            Self::SIZE,
+        );
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{label} Buffer")),
+            contents: bytemuck::bytes_of(&value),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{label} Bind Group Layout")),
+            entries: &Self::bind_group_layout_entries(visibility),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{label} Bind Group")),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            value,
+            dirty: false,
+            buffer,
+            layout,
+            bind_group,
+        }
+    }
+
+    /// `min_binding_size` 固定为 `T` 的大小，创建管线时 wgpu 会检查着色器需要的大小
+    pub fn bind_group_layout_entries(
+        visibility: wgpu::ShaderStages,
+    ) -> [wgpu::BindGroupLayoutEntry; 1] {
+        [wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: NonZeroU64::new(Self::SIZE),
+            },
+            count: None,
+        }]
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// 修改值，在下一次 [`UniformBuffer::flush`] 时写入缓冲
+    pub fn get_mut(&mut self) -> &mut T {
+        self.dirty = true;
+        &mut self.value
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// 值的字节与当前值不同时才写入缓冲，返回是否写入
+    pub fn update(&mut self, queue: &wgpu::Queue, value: &T) -> bool {
+        if bytemuck::bytes_of(&self.value) != bytemuck::bytes_of(value) {
+            self.value = *value;
+            self.dirty = true;
+        }
+        self.flush(queue)
+    }
+
+    /// 把通过 [`UniformBuffer::get_mut`] 做的修改写入缓冲，返回是否写入
+    pub fn flush(&mut self, queue: &wgpu::Queue) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.value));
+        self.dirty = false;
+        true
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::test_context;
+    use bytemuck::Zeroable;
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+    struct Params {
+        color: [f32; 3],
+        _padding: f32,
+        scale: [f32; 2],
+        _padding2: [f32; 2],
+    }
+
+    #[test]
+    fn layout_entry_uses_the_type_size() {
+        let [entry] =
+            UniformBuffer::<Params>::bind_group_layout_entries(wgpu::ShaderStages::FRAGMENT);
+        assert_eq!(
+            entry.ty,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: NonZeroU64::new(32),
+            }
+        );
+    }
+
+    #[test]
+    fn only_changed_values_are_written() {
+        let Some(ctx) = test_context() else {
+            return;
+        };
+
+        let mut params = Params::zeroed();
+        let mut uniform =
+            UniformBuffer::new(&ctx.device, "Params", wgpu::ShaderStages::FRAGMENT, params);
+        assert_eq!(uniform.buffer().size(), 32);
+        assert!(!uniform.is_dirty());
+        assert!(!uniform.update(&ctx.queue, &params));
+
+        params.scale = [2.0, 2.0];
+        assert!(uniform.update(&ctx.queue, &params));
+        assert_eq!(uniform.get(), &params);
+        assert!(!uniform.update(&ctx.queue, &params));
+
+        uniform.get_mut().color = [1.0, 0.0, 0.0];
+        assert!(uniform.is_dirty());
+        assert!(uniform.flush(&ctx.queue));
+        assert!(!uniform.flush(&ctx.queue));
+    }
+}
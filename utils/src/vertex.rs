@@ -0,0 +1,179 @@
+//! 从结构体字段生成顶点缓冲布局，避免手写的 `vertex_attr_array!` 与结构体不一致。
+//!
+//! ```ignore
+//! #[repr(C)]
+//! #[derive(Clone, Copy, Pod, Zeroable, VertexLayout)]
+//! struct Vertex {
+//!     position: [f32; 3],
+//!     tex_coord: [f32; 2],
+//! }
+//!
+//! let buffers = [Vertex::LAYOUT];
+//! ```
+
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4};
+use wgpu::VertexFormat;
+
+pub use utils_derive::VertexLayout;
+
+/// 可以作为顶点属性的字段类型，矩阵按列占用多个连续的 location
+pub trait VertexField {
+    const FORMATS: &'static [VertexFormat];
+}
+
+macro_rules! vertex_field {
+    ($($ty:ty => [$($format:ident),+]),* $(,)?) => {
+        $(
+            impl VertexField for $ty {
+                const FORMATS: &'static [VertexFormat] = &[$(VertexFormat::$format),+];
+            }
+        )*
+    };
+}
+
+vertex_field! {
+    f32 => [Float32],
+    [f32; 2] => [Float32x2],
+    [f32; 3] => [Float32x3],
+    [f32; 4] => [Float32x4],
+    u32 => [Uint32],
+    [u32; 2] => [Uint32x2],
+    [u32; 3] => [Uint32x3],
+    [u32; 4] => [Uint32x4],
+    i32 => [Sint32],
+    [i32; 2] => [Sint32x2],
+    [i32; 3] => [Sint32x3],
+    [i32; 4] => [Sint32x4],
+    [[f32; 3]; 3] => [Float32x3, Float32x3, Float32x3],
+    [[f32; 4]; 4] => [Float32x4, Float32x4, Float32x4, Float32x4],
+    Vec2 => [Float32x2],
+    Vec3 => [Float32x3],
+    Vec4 => [Float32x4],
+    Mat3 => [Float32x3, Float32x3, Float32x3],
+    Mat4 => [Float32x4, Float32x4, Float32x4, Float32x4],
+}
+
+/// 派生宏生成的一个字段
+#[doc(hidden)]
+pub struct Field {
+    pub formats: &'static [VertexFormat],
+    pub offset: wgpu::BufferAddress,
+    pub location: Option<u32>,
+}
+
+#[doc(hidden)]
+pub const fn attribute_count(fields: &[Field]) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        count += fields[i].formats.len();
+        i += 1;
+    }
+    count
+}
+
+/// 按字段顺序展开属性，`location` 没有指定时接着上一个属性编号
+#[doc(hidden)]
+pub const fn attributes<const N: usize>(fields: &[Field]) -> [wgpu::VertexAttribute; N] {
+    let mut attributes = [wgpu::VertexAttribute {
+        format: VertexFormat::Float32,
+        offset: 0,
+        shader_location: 0,
+    }; N];
+
+    let mut index = 0;
+    let mut location = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        let field = &fields[i];
+        if let Some(first) = field.location {
+            location = first;
+        }
+        let mut offset = field.offset;
+        let mut j = 0;
+        while j < field.formats.len() {
+            attributes[index] = wgpu::VertexAttribute {
+                format: field.formats[j],
+                offset,
+                shader_location: location,
+            };
+            offset += field.formats[j].size();
+            location += 1;
+            index += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+
+    let mut i = 0;
+    while i < N {
+        let mut j = i + 1;
+        while j < N {
+            if attributes[i].shader_location == attributes[j].shader_location {
+                panic!("two vertex attributes share a location");
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instance::InstanceRaw, model::ModelVertex};
+
+    #[test]
+    fn derived_layouts_match_the_handwritten_ones() {
+        assert_eq!(
+            ModelVertex::ATTRIBUTES,
+            wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x3, 3 => Float32x4]
+        );
+        assert_eq!(ModelVertex::LAYOUT.step_mode, wgpu::VertexStepMode::Vertex);
+        assert_eq!(
+            InstanceRaw::ATTRIBUTES,
+            wgpu::vertex_attr_array![
+                5 => Float32x4,
+                6 => Float32x4,
+                7 => Float32x4,
+                8 => Float32x4,
+                9 => Float32x4,
+            ]
+        );
+        assert_eq!(
+            InstanceRaw::LAYOUT.step_mode,
+            wgpu::VertexStepMode::Instance
+        );
+        assert_eq!(InstanceRaw::LAYOUT.array_stride, 80);
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, VertexLayout)]
+    struct Particle {
+        position: Vec3,
+        #[location(7)]
+        id: u32,
+        #[location(1)]
+        transform: Mat3,
+    }
+
+    #[test]
+    fn locations_continue_after_overrides() {
+        assert_eq!(
+            Particle::ATTRIBUTES,
+            [
+                (VertexFormat::Float32x3, 0, 0),
+                (VertexFormat::Uint32, 12, 7),
+                (VertexFormat::Float32x3, 16, 1),
+                (VertexFormat::Float32x3, 28, 2),
+                (VertexFormat::Float32x3, 40, 3),
+            ]
+            .map(|(format, offset, shader_location)| wgpu::VertexAttribute {
+                format,
+                offset,
+                shader_location,
+            })
+        );
+    }
+}
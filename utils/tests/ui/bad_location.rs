@@ -0,0 +1,10 @@
+use utils::vertex::VertexLayout;
+
+#[repr(C)]
+#[derive(Clone, Copy, VertexLayout)]
+struct Vertex {
+    #[location(first)]
+    position: [f32; 3],
+}
+
+fn main() {}
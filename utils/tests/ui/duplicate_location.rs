@@ -0,0 +1,11 @@
+use utils::vertex::VertexLayout;
+
+#[repr(C)]
+#[derive(Clone, Copy, VertexLayout)]
+struct Vertex {
+    position: [f32; 3],
+    #[location(0)]
+    tex_coords: [f32; 2],
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+//! `VertexLayout` 派生宏的编译错误信息，`TRYBUILD=overwrite cargo test -p utils --test vertex_layout_ui` 更新 `ui/*.stderr`
+
+#[test]
+fn vertex_layout_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}